@@ -7,6 +7,8 @@ use std::{
 
 use aptly_rest::{
     api::{publish, repos, snapshots::DeleteOptions},
+    args::AptlyArgs,
+    prefix::PublishPrefix,
     AptlyRest, AptlyRestError,
 };
 use clap::{builder::ArgPredicate, Parser};
@@ -14,7 +16,6 @@ use color_eyre::{
     eyre::{bail, ensure, Context},
     Result,
 };
-use http::StatusCode;
 use leon::Template;
 use reqwest::Client;
 use sync2aptly::{AptlyContent, PoolPackagesCache, UploadOptions};
@@ -25,17 +26,8 @@ use url::Url;
 
 #[derive(Parser, Debug)]
 struct Opts {
-    /// Url for the aptly rest api endpoint
-    #[clap(
-        short = 'u',
-        long,
-        env = "APTLY_API_URL",
-        default_value = "http://localhost:8080"
-    )]
-    api_url: url::Url,
-    /// Authentication token for the API
-    #[clap(long, env = "APTLY_API_TOKEN")]
-    api_token: Option<String>,
+    #[clap(flatten)]
+    aptly: AptlyArgs,
     /// Template to use as the aptly repo (use {component} to access the current
     /// component)
     aptly_repo_template: String,
@@ -52,7 +44,7 @@ struct Opts {
     create_aptly_repo: bool,
     /// Publish the repo and snapshots to the given prefix.
     #[clap(long = "publish-to")]
-    publish_prefix: Option<String>,
+    publish_prefix: Option<PublishPrefix>,
     /// Set the published release's 'Origin' to the given value.
     #[clap(long, requires_if(ArgPredicate::IsPresent, "publish_prefix"))]
     publish_origin: Option<String>,
@@ -83,6 +75,9 @@ struct Opts {
     /// Maximum number of parallel uploads
     #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..))]
     max_parallel: u8,
+    /// Run long aptly operations as background tasks
+    #[clap(long)]
+    async_tasks: bool,
     /// Only show changes, don't apply them
     #[clap(short = 'n', long, default_value_t = false)]
     dry_run: bool,
@@ -132,20 +127,10 @@ fn parse_snapshots_list(path: &Path) -> Result<Vec<String>> {
     Ok(lines)
 }
 
-fn is_error_not_found(e: &AptlyRestError) -> bool {
-    if let AptlyRestError::Request(e) = e {
-        if e.status() == Some(StatusCode::NOT_FOUND) {
-            return true;
-        }
-    }
-
-    false
-}
-
 async fn repo_exists(aptly: &AptlyRest, repo: &str) -> Result<bool> {
     match aptly.repo(repo).get().await {
         Ok(_) => Ok(true),
-        Err(e) if is_error_not_found(&e) => Ok(false),
+        Err(AptlyRestError::NotFound { .. }) => Ok(false),
         Err(e) => Err(e.into()),
     }
 }
@@ -153,7 +138,7 @@ async fn repo_exists(aptly: &AptlyRest, repo: &str) -> Result<bool> {
 async fn snapshot_exists(aptly: &AptlyRest, snapshot: &str) -> Result<bool> {
     match aptly.snapshot(snapshot).get().await {
         Ok(_) => Ok(true),
-        Err(e) if is_error_not_found(&e) => Ok(false),
+        Err(AptlyRestError::NotFound { .. }) => Ok(false),
         Err(e) => Err(e.into()),
     }
 }
@@ -165,7 +150,7 @@ async fn snapshot_delete(aptly: &AptlyRest, snapshot: &str) -> Result<bool> {
         .await
     {
         Ok(_) => Ok(true),
-        Err(e) if is_error_not_found(&e) => Ok(false),
+        Err(AptlyRestError::NotFound { .. }) => Ok(false),
         Err(e) => Err(e.into()),
     }
 }
@@ -207,7 +192,7 @@ struct AptRepo<'s, 't> {
 
 #[derive(Hash, PartialEq, Eq)]
 struct AptlyPublishedDist {
-    prefix: String,
+    prefix: PublishPrefix,
     distribution: String,
 }
 
@@ -222,7 +207,7 @@ impl AptlyPublishedCache {
                 .await?
                 .into_iter()
                 .map(|p| AptlyPublishedDist {
-                    prefix: p.prefix().to_owned(),
+                    prefix: p.publish_prefix(),
                     distribution: p.distribution().to_owned(),
                 })
                 .collect(),
@@ -344,6 +329,7 @@ async fn sync_dist(
                     "apt2aptly",
                     &UploadOptions {
                         max_parallel: opts.max_parallel,
+                        async_tasks: opts.async_tasks,
                     },
                 )
                 .await?;
@@ -428,16 +414,23 @@ async fn sync_dist(
                     publish_prefix, dist_path
                 );
 
-                aptly
-                    .publish_prefix(publish_prefix)
-                    .distribution(dist_path)
-                    .update(&publish::UpdateOptions {
-                        signing: Some(signing),
-                        skip_bz2: true,
-                        skip_contents: true,
-                        ..Default::default()
-                    })
-                    .await?;
+                let options = publish::UpdateOptions {
+                    signing: Some(signing),
+                    skip_bz2: true,
+                    skip_contents: true,
+                    ..Default::default()
+                };
+                let publish_api = aptly.publish_prefix(publish_prefix);
+                let distribution = publish_api.distribution(dist_path);
+                if opts.async_tasks {
+                    distribution
+                        .update_async(&options)
+                        .await?
+                        .result::<publish::PublishedRepo>()
+                        .await?;
+                } else {
+                    distribution.update(&options).await?;
+                }
             } else {
                 info!(
                     "Publishing to {}/{} ({})...",
@@ -446,23 +439,26 @@ async fn sync_dist(
                     architectures.join(" "),
                 );
 
-                aptly
-                    .publish_prefix(publish_prefix)
-                    .publish(
-                        kind,
-                        &sources,
-                        &publish::PublishOptions {
-                            distribution: Some(dist_path),
-                            architectures,
-                            signing: Some(signing),
-                            skip_bz2: true,
-                            skip_contents: true,
-                            origin: opts.publish_origin.clone(),
-                            label: opts.publish_label.clone(),
-                            ..Default::default()
-                        },
-                    )
-                    .await?;
+                let options = publish::PublishOptions {
+                    distribution: Some(dist_path),
+                    architectures,
+                    signing: Some(signing),
+                    skip_bz2: true,
+                    skip_contents: true,
+                    origin: opts.publish_origin.clone(),
+                    label: opts.publish_label.clone(),
+                    ..Default::default()
+                };
+                let publish_api = aptly.publish_prefix(publish_prefix);
+                if opts.async_tasks {
+                    publish_api
+                        .publish_async(kind, &sources, &options)
+                        .await?
+                        .result::<publish::PublishedRepo>()
+                        .await?;
+                } else {
+                    publish_api.publish(kind, &sources, &options).await?;
+                }
             }
         }
 
@@ -480,11 +476,7 @@ async fn main() -> Result<()> {
         .init();
     color_eyre::install().unwrap();
     let opts = Opts::parse();
-    let aptly = if let Some(token) = &opts.api_token {
-        AptlyRest::new_with_token(opts.api_url.clone(), token)?
-    } else {
-        AptlyRest::new(opts.api_url.clone())
-    };
+    let aptly = opts.aptly.build()?;
 
     let aptly_repo_template = if opts.static_aptly_repo_name {
         MaybeTemplate::Static(opts.aptly_repo_template.clone())
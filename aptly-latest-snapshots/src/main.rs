@@ -1,7 +1,7 @@
 use std::{net::SocketAddr, time::Duration};
 
 use aptly_latest_snapshots::{create_app, periodic_snapshot_refresh, AppState};
-use aptly_rest::AptlyRest;
+use aptly_rest::args::AptlyArgs;
 use clap::Parser;
 use color_eyre::{eyre::WrapErr, Result};
 use tracing::info;
@@ -11,17 +11,8 @@ use tracing_subscriber::prelude::*;
 
 #[derive(Parser, Debug)]
 struct Opts {
-    /// Url for the aptly rest api endpoint
-    #[clap(
-        short = 'u',
-        long,
-        env = "APTLY_API_URL",
-        default_value = "http://localhost:8080"
-    )]
-    api_url: url::Url,
-    /// Authentication token for the API
-    #[clap(long, env = "APTLY_API_TOKEN")]
-    api_token: Option<String>,
+    #[clap(flatten)]
+    aptly: AptlyArgs,
     /// Address and port to bind to
     #[clap(long = "bind-to", default_value = "0.0.0.0:8080")]
     bind_addr: SocketAddr,
@@ -42,11 +33,7 @@ async fn main() -> Result<()> {
     color_eyre::install().unwrap();
 
     let opts = Opts::parse();
-    let aptly = if let Some(token) = &opts.api_token {
-        AptlyRest::new_with_token(opts.api_url.clone(), token)?
-    } else {
-        AptlyRest::new(opts.api_url.clone())
-    };
+    let aptly = opts.aptly.build()?;
 
     let state = AppState::new(&aptly).await?;
 
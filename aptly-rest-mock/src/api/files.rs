@@ -0,0 +1,20 @@
+use wiremock::{Respond, ResponseTemplate};
+
+pub(crate) struct FilesUploadResponder;
+
+impl Respond for FilesUploadResponder {
+    fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+        let directory = request.url.path_segments().unwrap().nth(2).unwrap();
+
+        // Just enough multipart parsing to report the uploaded file names
+        let body = String::from_utf8_lossy(&request.body);
+        let uploaded: Vec<_> = body
+            .split("filename=\"")
+            .skip(1)
+            .filter_map(|part| part.split_once('"'))
+            .map(|(filename, _)| format!("{directory}/{filename}"))
+            .collect();
+
+        ResponseTemplate::new(200).set_body_json(uploaded)
+    }
+}
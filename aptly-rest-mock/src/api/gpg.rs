@@ -0,0 +1,59 @@
+use serde::Deserialize;
+use serde_json::json;
+use wiremock::{Respond, ResponseTemplate};
+
+use crate::AptlyRestMock;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct ImportKeyRequest {
+    #[serde(default)]
+    gpg_key_armor: String,
+    #[serde(default, rename = "GpgKeyID")]
+    gpg_key_id: String,
+    #[serde(default)]
+    keyring: String,
+}
+
+pub(crate) struct GpgKeyResponder {
+    mock: AptlyRestMock,
+}
+
+impl GpgKeyResponder {
+    pub(crate) fn new(mock: AptlyRestMock) -> Self {
+        Self { mock }
+    }
+}
+
+impl Respond for GpgKeyResponder {
+    fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+        let Ok(request) = serde_json::from_slice::<ImportKeyRequest>(&request.body) else {
+            return ResponseTemplate::new(400).set_body_json(json!({"error": "invalid request"}));
+        };
+
+        let keys: Vec<String> = if request.gpg_key_armor.is_empty() {
+            request
+                .gpg_key_id
+                .split_whitespace()
+                .map(ToOwned::to_owned)
+                .collect()
+        } else {
+            vec![request.gpg_key_armor]
+        };
+        if keys.is_empty() {
+            return ResponseTemplate::new(400).set_body_json(json!({"error": "no keys to import"}));
+        }
+
+        let keyring = if request.keyring.is_empty() {
+            "trustedkeys.gpg".to_owned()
+        } else {
+            request.keyring
+        };
+        let output = format!("gpg: Total number processed: {}\n", keys.len());
+
+        let mut inner = self.mock.inner.write().unwrap();
+        inner.gpg_keys.entry(keyring).or_default().extend(keys);
+
+        ResponseTemplate::new(200).set_body_json(output)
+    }
+}
@@ -0,0 +1,168 @@
+use percent_encoding::percent_decode_str;
+use serde_json::{json, Map, Value};
+use wiremock::{Respond, ResponseTemplate};
+
+use crate::AptlyRestMock;
+
+/// Settings of a mirror that can be set on creation or changed by an edit,
+/// as named in requests and in the mirror itself
+const SETTINGS: [&str; 9] = [
+    "Distribution",
+    "Components",
+    "Architectures",
+    "Filter",
+    "FilterWithDeps",
+    "DownloadSources",
+    "DownloadUdebs",
+    "DownloadInstaller",
+    "SkipComponentCheck",
+];
+
+fn error(status: u16, message: String) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(json!({ "error": message }))
+}
+
+fn not_found(name: &str) -> ResponseTemplate {
+    error(404, format!("mirror with name {name} not found"))
+}
+
+pub(crate) struct MirrorsResponder {
+    mock: AptlyRestMock,
+}
+
+impl MirrorsResponder {
+    pub(crate) fn new(mock: AptlyRestMock) -> Self {
+        Self { mock }
+    }
+
+    fn create(&self, body: Map<String, Value>) -> ResponseTemplate {
+        let (Some(name), Some(archive_url)) = (
+            body.get("Name").and_then(Value::as_str),
+            body.get("ArchiveURL").and_then(Value::as_str),
+        ) else {
+            return error(400, "missing name or archive url".to_owned());
+        };
+
+        let mut inner = self.mock.inner.write().unwrap();
+        if inner.mirrors.contains_key(name) {
+            return error(400, format!("mirror with name {name} already exists"));
+        }
+
+        let mut mirror = Map::new();
+        mirror.insert("UUID".to_owned(), json!(format!("mirror-{name}")));
+        mirror.insert("Name".to_owned(), json!(name));
+        mirror.insert("ArchiveRoot".to_owned(), json!(archive_url));
+        mirror.insert("Status".to_owned(), json!(0));
+        for setting in SETTINGS {
+            if let Some(value) = body.get(setting) {
+                mirror.insert(setting.to_owned(), value.clone());
+            }
+        }
+
+        let mirror = Value::Object(mirror);
+        inner.mirrors.insert(name.to_owned(), mirror.clone());
+        ResponseTemplate::new(201).set_body_json(mirror)
+    }
+
+    fn edit(&self, name: &str, body: Map<String, Value>) -> ResponseTemplate {
+        let mut inner = self.mock.inner.write().unwrap();
+        let Some(mut mirror) = inner.mirrors.remove(name) else {
+            return not_found(name);
+        };
+
+        let fields = mirror.as_object_mut().unwrap();
+        for setting in SETTINGS {
+            if let Some(value) = body.get(setting) {
+                fields.insert(setting.to_owned(), value.clone());
+            }
+        }
+        if let Some(archive_url) = body.get("ArchiveURL") {
+            fields.insert("ArchiveRoot".to_owned(), archive_url.clone());
+        }
+        // Edits are applied by updating, which downloads the mirror
+        fields.insert(
+            "LastDownloadDate".to_owned(),
+            json!("2024-11-19T09:39:02.123456789Z"),
+        );
+        let new_name = match body.get("Name").and_then(Value::as_str) {
+            Some(new_name) => new_name.to_owned(),
+            None => name.to_owned(),
+        };
+        fields.insert("Name".to_owned(), json!(new_name));
+
+        inner.mirrors.insert(new_name, mirror.clone());
+        ResponseTemplate::new(200).set_body_json(mirror)
+    }
+
+    fn snapshot(&self, name: &str, body: Map<String, Value>) -> ResponseTemplate {
+        let Some(snapshot) = body.get("Name").and_then(Value::as_str) else {
+            return error(400, "missing snapshot name".to_owned());
+        };
+        // aptly only accepts a string here
+        let description = match body.get("Description") {
+            None => format!("Snapshot from mirror {name}"),
+            Some(Value::String(description)) => description.clone(),
+            Some(_) => return error(400, "invalid description".to_owned()),
+        };
+
+        let mut inner = self.mock.inner.write().unwrap();
+        if !inner.mirrors.contains_key(name) {
+            return not_found(name);
+        }
+        let snapshot = json!({
+            "Name": snapshot,
+            "Description": description,
+            "CreatedAt": "2024-11-19T10:00:00Z",
+        });
+        inner.mirror_snapshots.push(snapshot.clone());
+        ResponseTemplate::new(201).set_body_json(snapshot)
+    }
+}
+
+impl Respond for MirrorsResponder {
+    fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+        let segments: Vec<_> = request
+            .url
+            .path_segments()
+            .unwrap()
+            .skip(2)
+            .map(|s| percent_decode_str(s).decode_utf8_lossy().into_owned())
+            .collect();
+        let body = || serde_json::from_slice::<Map<String, Value>>(&request.body);
+
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", []) => {
+                let inner = self.mock.inner.read().unwrap();
+                let mirrors: Vec<_> = inner.mirrors.values().collect();
+                ResponseTemplate::new(200).set_body_json(mirrors)
+            }
+            ("POST", []) => match body() {
+                Ok(body) => self.create(body),
+                Err(e) => error(400, e.to_string()),
+            },
+            ("GET", [name]) => {
+                let inner = self.mock.inner.read().unwrap();
+                match inner.mirrors.get(name) {
+                    Some(mirror) => ResponseTemplate::new(200).set_body_json(mirror),
+                    None => not_found(name),
+                }
+            }
+            ("PUT", [name]) => match body() {
+                Ok(body) => self.edit(name, body),
+                Err(e) => error(400, e.to_string()),
+            },
+            ("DELETE", [name]) => {
+                let mut inner = self.mock.inner.write().unwrap();
+                match inner.mirrors.remove(name) {
+                    Some(_) => ResponseTemplate::new(200).set_body_json(json!({})),
+                    None => not_found(name),
+                }
+            }
+            ("POST", [name, snapshots]) if snapshots == "snapshots" => match body() {
+                Ok(body) => self.snapshot(name, body),
+                Err(e) => error(400, e.to_string()),
+            },
+            _ => ResponseTemplate::new(404),
+        }
+    }
+}
@@ -1,2 +1,9 @@
+pub(crate) mod files;
+pub(crate) mod gpg;
+pub(crate) mod mirrors;
 pub(crate) mod packages;
 pub(crate) mod repos;
+pub(crate) mod snapshots;
+pub(crate) mod status;
+pub(crate) mod tasks;
+pub(crate) mod version;
@@ -1,3 +1,5 @@
+use percent_encoding::percent_decode_str;
+use serde_json::json;
 use wiremock::{Respond, ResponseTemplate};
 
 use crate::AptlyRestMock;
@@ -15,6 +17,29 @@ impl PackagesResponder {
 
 impl Respond for PackagesResponder {
     fn respond(&self, _request: &wiremock::Request) -> wiremock::ResponseTemplate {
-        ResponseTemplate::new(200).set_body_json(serde_json::json!([]))
+        ResponseTemplate::new(200).set_body_json(json!([]))
+    }
+}
+
+pub(crate) struct PackageResponder {
+    mock: AptlyRestMock,
+}
+
+impl PackageResponder {
+    pub(crate) fn new(mock: AptlyRestMock) -> Self {
+        Self { mock }
+    }
+}
+
+impl Respond for PackageResponder {
+    fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+        let key = request.url.path_segments().unwrap().nth(2).unwrap();
+        let key = percent_decode_str(key).decode_utf8_lossy();
+
+        let inner = self.mock.inner.read().unwrap();
+        match inner.pool.package(&key) {
+            Some(package) => ResponseTemplate::new(200).set_body_json(package.fields()),
+            None => ResponseTemplate::new(404).set_body_json(json!({"error": "key not found"})),
+        }
     }
 }
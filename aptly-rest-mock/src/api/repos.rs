@@ -70,7 +70,9 @@ impl Respond for ReposPackagesResponder {
                 ResponseTemplate::new(200).set_body_json(repo.packages())
             }
         } else {
-            ResponseTemplate::new(404)
+            ResponseTemplate::new(404).set_body_json(json!({
+                "error": format!("local repo with name {name} not found")
+            }))
         }
     }
 }
@@ -0,0 +1,89 @@
+use serde::Deserialize;
+use serde_json::json;
+use wiremock::{Respond, ResponseTemplate};
+
+use crate::AptlyRestMock;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct CreateRequest {
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    source_snapshots: Vec<String>,
+    #[serde(default)]
+    package_refs: Vec<String>,
+}
+
+fn error(status: u16, message: String) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(json!({ "error": message }))
+}
+
+pub(crate) struct SnapshotsResponder {
+    mock: AptlyRestMock,
+}
+
+impl SnapshotsResponder {
+    pub(crate) fn new(mock: AptlyRestMock) -> Self {
+        Self { mock }
+    }
+}
+
+impl Respond for SnapshotsResponder {
+    fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+        let request = match serde_json::from_slice::<CreateRequest>(&request.body) {
+            Ok(request) => request,
+            Err(e) => return error(400, e.to_string()),
+        };
+        if request.name.is_empty() {
+            return error(400, "missing snapshot name".to_owned());
+        }
+
+        let mut inner = self.mock.inner.write().unwrap();
+        if inner.snapshots.contains_key(&request.name) {
+            return error(
+                400,
+                format!("snapshot with name {} already exists", request.name),
+            );
+        }
+        let known = |name: &String| {
+            inner.snapshots.contains_key(name)
+                || inner.mirror_snapshots.iter().any(|s| s["Name"] == **name)
+        };
+        if let Some(source) = request.source_snapshots.iter().find(|s| !known(s)) {
+            return error(404, format!("snapshot with name {source} not found"));
+        }
+        if let Some(key) = request
+            .package_refs
+            .iter()
+            .find(|k| !inner.pool.has_package(k))
+        {
+            return error(404, format!("package {key}: key not found"));
+        }
+
+        // Like aptly, describe the snapshot by its sources unless told otherwise
+        let description = match request.description.filter(|d| !d.is_empty()) {
+            Some(description) => description,
+            None if request.source_snapshots.is_empty() => "Created as empty".to_owned(),
+            None => format!(
+                "Merged from sources: {}",
+                request
+                    .source_snapshots
+                    .iter()
+                    .map(|s| format!("'{s}'"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        let snapshot = json!({
+            "Name": request.name,
+            "Description": description,
+            "CreatedAt": "2024-11-19T10:00:00Z",
+        });
+        inner
+            .snapshots
+            .insert(request.name, (snapshot.clone(), request.package_refs));
+        ResponseTemplate::new(201).set_body_json(snapshot)
+    }
+}
@@ -0,0 +1,36 @@
+use serde_json::json;
+use wiremock::{Respond, ResponseTemplate};
+
+use crate::AptlyRestMock;
+
+pub(crate) const METRICS: &str = r#"# HELP aptly_api_http_requests_in_flight Number of concurrent HTTP api requests currently handled.
+# TYPE aptly_api_http_requests_in_flight gauge
+aptly_api_http_requests_in_flight{method="GET",path="/api/metrics"} 1
+# HELP aptly_api_http_requests_total Total number of api requests.
+# TYPE aptly_api_http_requests_total counter
+aptly_api_http_requests_total{code="200",method="GET",path="/api/version"} 3
+# HELP aptly_build_info Metric with a constant '1' value labeled by version and goversion from which aptly was built.
+# TYPE aptly_build_info gauge
+aptly_build_info{goversion="go1.22.2",version="1.6.0"} 1
+"#;
+
+pub(crate) struct ReadyResponder {
+    mock: AptlyRestMock,
+}
+
+impl ReadyResponder {
+    pub(crate) fn new(mock: AptlyRestMock) -> Self {
+        Self { mock }
+    }
+}
+
+impl Respond for ReadyResponder {
+    fn respond(&self, _request: &wiremock::Request) -> wiremock::ResponseTemplate {
+        let inner = self.mock.inner.read().unwrap();
+        if inner.ready {
+            ResponseTemplate::new(200).set_body_json(json!({"Status": "Aptly is ready"}))
+        } else {
+            ResponseTemplate::new(503).set_body_json(json!({"Status": "Aptly is unavailable"}))
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use serde_json::json;
+use wiremock::{Respond, ResponseTemplate};
+
+use crate::AptlyRestMock;
+
+pub(crate) struct TaskResponder {
+    mock: AptlyRestMock,
+}
+
+impl TaskResponder {
+    pub(crate) fn new(mock: AptlyRestMock) -> Self {
+        Self { mock }
+    }
+}
+
+impl Respond for TaskResponder {
+    fn respond(&self, request: &wiremock::Request) -> wiremock::ResponseTemplate {
+        let mut segments = request.url.path_segments().unwrap().skip(2);
+        let id = segments.next().and_then(|id| id.parse::<u64>().ok());
+        let action = segments.next();
+
+        let inner = self.mock.inner.read().unwrap();
+        let Some(task) = id.and_then(|id| inner.tasks.get(&id)) else {
+            return ResponseTemplate::new(404).set_body_json(json!({"error": "task not found"}));
+        };
+        match action {
+            None => ResponseTemplate::new(200).set_body_json(json!({
+                "ID": id,
+                "Name": task.name,
+                "State": task.state,
+            })),
+            Some("output") => ResponseTemplate::new(200).set_body_json(&task.output),
+            Some("return_value") => ResponseTemplate::new(200).set_body_json(json!({})),
+            Some(_) => ResponseTemplate::new(404),
+        }
+    }
+}
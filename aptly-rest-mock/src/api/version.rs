@@ -0,0 +1,21 @@
+use serde_json::json;
+use wiremock::{Respond, ResponseTemplate};
+
+use crate::AptlyRestMock;
+
+pub(crate) struct VersionResponder {
+    mock: AptlyRestMock,
+}
+
+impl VersionResponder {
+    pub(crate) fn new(mock: AptlyRestMock) -> Self {
+        Self { mock }
+    }
+}
+
+impl Respond for VersionResponder {
+    fn respond(&self, _request: &wiremock::Request) -> wiremock::ResponseTemplate {
+        let inner = self.mock.inner.read().unwrap();
+        ResponseTemplate::new(200).set_body_json(json!({ "Version": inner.version }))
+    }
+}
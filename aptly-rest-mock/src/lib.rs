@@ -1,9 +1,9 @@
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::RwLock;
 
-use http::StatusCode;
 use pool::Package;
 use repo::Repositories;
 use serde::Deserialize;
@@ -12,44 +12,118 @@ use url::Url;
 use wiremock::matchers::method;
 use wiremock::matchers::path;
 use wiremock::matchers::path_regex;
-use wiremock::ResponseTemplate;
-use wiremock::{Mock, MockServer};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 mod api;
 mod pool;
 mod repo;
 use pool::Pool;
 
+/// Version the mock reports unless changed with [AptlyRestMock::set_version]
 pub const APTLY_VERSION: &str = "1.4.0+187+g15f2c97d";
 
 struct Inner {
     pool: Pool,
     repositories: Repositories,
+    version: String,
+    /// Keys imported into each keyring, by id or as armored text
+    gpg_keys: BTreeMap<String, Vec<String>>,
+    ready: bool,
+    /// Mirrors by name, as aptly returns them
+    mirrors: BTreeMap<String, serde_json::Value>,
+    /// Snapshots created from mirrors, as aptly returns them
+    mirror_snapshots: Vec<serde_json::Value>,
+    /// Snapshots created with `/api/snapshots` as aptly returns them, with
+    /// their package keys
+    snapshots: BTreeMap<String, (serde_json::Value, Vec<String>)>,
+    tasks: BTreeMap<u64, Task>,
+}
+
+struct Task {
+    name: String,
+    state: u8,
+    output: String,
+}
+
+/// Forwards connections on a unix socket to the mock server
+#[cfg(unix)]
+struct UnixSocketForward {
+    path: PathBuf,
+    _dir: tempfile::TempDir,
+    task: tokio::task::JoinHandle<()>,
+}
+
+#[cfg(unix)]
+impl UnixSocketForward {
+    fn start(target: std::net::SocketAddr) -> Self {
+        let dir = tempfile::tempdir().expect("Failed to create socket directory");
+        let path = dir.path().join("aptly.sock");
+        let listener = tokio::net::UnixListener::bind(&path).expect("Failed to bind socket");
+
+        let task = tokio::spawn(async move {
+            while let Ok((mut incoming, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut outgoing = tokio::net::TcpStream::connect(target).await?;
+                    tokio::io::copy_bidirectional(&mut incoming, &mut outgoing).await
+                });
+            }
+        });
+
+        Self {
+            path,
+            _dir: dir,
+            task,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for UnixSocketForward {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
 }
 
 #[derive(Clone)]
 pub struct AptlyRestMock {
     server: Arc<MockServer>,
     inner: Arc<RwLock<Inner>>,
+    #[cfg(unix)]
+    socket: Option<Arc<UnixSocketForward>>,
 }
 
 impl AptlyRestMock {
+    /// Start a mock that serves the aptly API over a unix socket, so
+    /// [AptlyRestMock::url] is a `unix://` location
+    #[cfg(unix)]
+    pub async fn start_unix() -> Self {
+        let mut mock = Self::start().await;
+        mock.socket = Some(Arc::new(UnixSocketForward::start(*mock.server.address())));
+        mock
+    }
+
     pub async fn start() -> Self {
         let inner = Arc::new(RwLock::new(Inner {
             pool: Pool::new(),
             repositories: Repositories::new(),
+            version: APTLY_VERSION.to_owned(),
+            gpg_keys: BTreeMap::new(),
+            ready: true,
+            mirrors: BTreeMap::new(),
+            mirror_snapshots: Vec::new(),
+            snapshots: BTreeMap::new(),
+            tasks: BTreeMap::new(),
         }));
         let server = AptlyRestMock {
             server: Arc::new(MockServer::start().await),
             inner,
+            #[cfg(unix)]
+            socket: None,
         };
 
         Mock::given(method("GET"))
             .and(path("api/version"))
-            .respond_with(
-                ResponseTemplate::new(StatusCode::OK)
-                    .set_body_json(json!({ "Version": APTLY_VERSION })),
-            )
+            .respond_with(api::version::VersionResponder::new(server.clone()))
             .mount(&server.server)
             .await;
 
@@ -59,6 +133,12 @@ impl AptlyRestMock {
             .mount(&server.server)
             .await;
 
+        Mock::given(method("GET"))
+            .and(path_regex("api/packages/[^/]+$"))
+            .respond_with(api::packages::PackageResponder::new(server.clone()))
+            .mount(&server.server)
+            .await;
+
         Mock::given(method("GET"))
             .and(path("api/repos"))
             .respond_with(api::repos::ReposResponder::new(server.clone()))
@@ -71,6 +151,71 @@ impl AptlyRestMock {
             .mount(&server.server)
             .await;
 
+        Mock::given(method("POST"))
+            .and(path("api/snapshots"))
+            .respond_with(api::snapshots::SnapshotsResponder::new(server.clone()))
+            .mount(&server.server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path_regex("api/files/[^/]*$"))
+            .respond_with(api::files::FilesUploadResponder)
+            .mount(&server.server)
+            .await;
+
+        Mock::given(path_regex("api/mirrors(/.*)?$"))
+            .respond_with(api::mirrors::MirrorsResponder::new(server.clone()))
+            .mount(&server.server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path_regex("api/tasks/[^/]+(/[^/]+)?$"))
+            .respond_with(api::tasks::TaskResponder::new(server.clone()))
+            .mount(&server.server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("api/gpg/key"))
+            .respond_with(api::gpg::GpgKeyResponder::new(server.clone()))
+            .mount(&server.server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("api/storage"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "Total": 102400,
+                "Free": 25600,
+                "PercentFull": 75.0,
+            })))
+            .mount(&server.server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("api/healthy"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"Status": "Aptly is healthy"})),
+            )
+            .mount(&server.server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("api/ready"))
+            .respond_with(api::status::ReadyResponder::new(server.clone()))
+            .mount(&server.server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("api/metrics"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(api::status::METRICS))
+            .mount(&server.server)
+            .await;
+
+        Mock::given(method("POST"))
+            .and(path("api/db/recover"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(null)))
+            .mount(&server.server)
+            .await;
+
         server
     }
 
@@ -119,7 +264,98 @@ impl AptlyRestMock {
         inner.repositories.add_package(repo, key);
     }
 
+    /// Change the version of aptly the mock reports
+    pub fn set_version(&self, version: &str) {
+        let mut inner = self.inner.write().unwrap();
+        inner.version = version.to_owned();
+    }
+
+    /// Change whether the mock reports to be ready to handle requests
+    pub fn set_ready(&self, ready: bool) {
+        let mut inner = self.inner.write().unwrap();
+        inner.ready = ready;
+    }
+
+    /// Mirrors the mock knows about, as aptly returns them
+    pub fn mirrors(&self) -> Vec<serde_json::Value> {
+        let inner = self.inner.read().unwrap();
+        inner.mirrors.values().cloned().collect()
+    }
+
+    /// Snapshots created from mirrors, as aptly returns them
+    pub fn mirror_snapshots(&self) -> Vec<serde_json::Value> {
+        let inner = self.inner.read().unwrap();
+        inner.mirror_snapshots.clone()
+    }
+
+    /// Snapshots created with `/api/snapshots`, as aptly returns them
+    pub fn snapshots(&self) -> Vec<serde_json::Value> {
+        let inner = self.inner.read().unwrap();
+        inner.snapshots.values().map(|(s, _)| s.clone()).collect()
+    }
+
+    /// Package keys of a snapshot created with `/api/snapshots`
+    pub fn snapshot_packages(&self, name: &str) -> Option<Vec<String>> {
+        let inner = self.inner.read().unwrap();
+        inner.snapshots.get(name).map(|(_, keys)| keys.clone())
+    }
+
+    /// Add or replace a background task; `state` is aptly's numeric task
+    /// state, e.g. 1 for running and 3 for failed
+    pub fn set_task(&self, id: u64, name: &str, state: u8, output: &str) {
+        let mut inner = self.inner.write().unwrap();
+        inner.tasks.insert(
+            id,
+            Task {
+                name: name.to_owned(),
+                state,
+                output: output.to_owned(),
+            },
+        );
+    }
+
+    /// Answer the next `times` requests for `method` and `path` with the
+    /// given status instead of handling them
+    pub async fn fail_requests(&self, method_: &str, path_: &str, status: u16, times: u64) {
+        Mock::given(method(method_))
+            .and(path(path_))
+            .respond_with(ResponseTemplate::new(status))
+            .up_to_n_times(times)
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Number of requests received for `method` and `path`
+    pub async fn received_requests(&self, method_: &str, path_: &str) -> usize {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter(|r| r.method.as_str() == method_ && r.url.path() == path_)
+            .count()
+    }
+
+    /// JSON bodies of the requests received for `method` and `path`
+    pub async fn received_bodies(&self, method_: &str, path_: &str) -> Vec<serde_json::Value> {
+        self.server
+            .received_requests()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .filter(|r| r.method.as_str() == method_ && r.url.path() == path_)
+            .map(|r| serde_json::from_slice(&r.body).expect("body is not JSON"))
+            .collect()
+    }
+
     pub fn url(&self) -> Url {
+        #[cfg(unix)]
+        if let Some(socket) = &self.socket {
+            let mut url = Url::parse("unix:///").unwrap();
+            url.set_path(socket.path.to_str().expect("socket path is not UTF-8"));
+            return url;
+        }
         self.server.uri().parse().expect("uri is not a url")
     }
 
@@ -132,6 +368,12 @@ impl AptlyRestMock {
         let inner = self.inner.read().unwrap();
         inner.pool.package(key).cloned()
     }
+
+    /// Keys imported into a keyring, by id or as armored text
+    pub fn gpg_keys(&self, keyring: &str) -> Vec<String> {
+        let inner = self.inner.read().unwrap();
+        inner.gpg_keys.get(keyring).cloned().unwrap_or_default()
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -1,23 +1,86 @@
+use std::{
+    io,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{ready, Context, Poll},
+};
+
+use digest::Digest;
 use reqwest::Url;
-use tokio::io::AsyncRead;
+use tokio::io::{AsyncRead, ReadBuf};
 use tokio_util::codec::{BytesCodec, FramedRead};
 
-use crate::AptlyRestError;
+use crate::{changes::ChangesFile, AptlyRestError};
+
+/// The size and checksums a file is expected to have; checksums that are
+/// `None` aren't verified
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checksums {
+    pub size: Option<u64>,
+    pub md5: Option<String>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+}
+
+impl From<&ChangesFile> for Checksums {
+    fn from(file: &ChangesFile) -> Self {
+        Self {
+            size: Some(file.size),
+            md5: Some(file.md5.clone()),
+            sha1: Some(file.sha1.clone()),
+            sha256: Some(file.sha256.clone()),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("Uploaded {filename} has {kind} {actual}, expected {expected}")]
+pub struct ChecksumError {
+    pub filename: String,
+    /// Which of the checksums didn't match: `size`, `md5`, `sha1` or `sha256`
+    pub kind: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Progress of an upload, reported every time a chunk of a file has been
+/// read to be sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadProgress {
+    pub filename: String,
+    /// Bytes of this file sent so far
+    pub file_bytes: u64,
+    /// Size of this file, if it was given with its checksums
+    pub file_size: Option<u64>,
+    /// Bytes of all files sent so far
+    pub total_bytes: u64,
+    /// Size of all files, if every one of them was given with its checksums
+    pub total_size: Option<u64>,
+}
+
+type ProgressCallback = Arc<dyn Fn(&UploadProgress) + Send + Sync>;
+
+struct UploadFile {
+    filename: String,
+    contents: Pin<Box<dyn AsyncRead + Send + Sync>>,
+    checksums: Checksums,
+}
 
 #[derive(Default)]
 pub struct UploadFiles {
-    parts: Vec<reqwest::multipart::Part>,
+    files: Vec<UploadFile>,
 }
 
 impl UploadFiles {
     pub fn new() -> Self {
-        Self { parts: vec![] }
+        Self { files: vec![] }
     }
 
     pub fn add_file(&mut self, filename: String, contents: impl AsyncRead + Send + Sync + 'static) {
-        let body = reqwest::Body::wrap_stream(FramedRead::new(contents, BytesCodec::new()));
-        self.parts
-            .push(reqwest::multipart::Part::stream(body).file_name(filename));
+        self.add_verified_file(filename, contents, Checksums::default());
     }
 
     pub fn file(
@@ -28,6 +91,155 @@ impl UploadFiles {
         self.add_file(filename, contents);
         self
     }
+
+    /// Add a file whose contents are verified against `checksums` while
+    /// they're sent; on a mismatch the upload is aborted with
+    /// [`AptlyRestError::Checksum`]
+    pub fn add_verified_file(
+        &mut self,
+        filename: String,
+        contents: impl AsyncRead + Send + Sync + 'static,
+        checksums: Checksums,
+    ) {
+        self.files.push(UploadFile {
+            filename,
+            contents: Box::pin(contents),
+            checksums,
+        });
+    }
+
+    pub fn verified_file(
+        mut self,
+        filename: String,
+        contents: impl AsyncRead + Send + Sync + 'static,
+        checksums: Checksums,
+    ) -> Self {
+        self.add_verified_file(filename, contents, checksums);
+        self
+    }
+}
+
+/// Shared state of the files of a single upload
+struct UploadState {
+    total_bytes: AtomicU64,
+    total_size: Option<u64>,
+    progress: Option<ProgressCallback>,
+    failure: Mutex<Option<ChecksumError>>,
+}
+
+/// Reads the contents of an uploaded file, keeping track of the progress and
+/// checksums along the way
+struct UploadReader {
+    contents: Pin<Box<dyn AsyncRead + Send + Sync>>,
+    checksums: Checksums,
+    size: u64,
+    md5: Option<md5::Md5>,
+    sha1: Option<sha1::Sha1>,
+    sha256: Option<sha2::Sha256>,
+    filename: String,
+    state: Arc<UploadState>,
+}
+
+impl UploadReader {
+    fn new(file: UploadFile, state: Arc<UploadState>) -> Self {
+        let checksums = file.checksums;
+        Self {
+            contents: file.contents,
+            md5: checksums.md5.as_ref().map(|_| Digest::new()),
+            sha1: checksums.sha1.as_ref().map(|_| Digest::new()),
+            sha256: checksums.sha256.as_ref().map(|_| Digest::new()),
+            checksums,
+            size: 0,
+            filename: file.filename,
+            state,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.size += data.len() as u64;
+        if let Some(h) = &mut self.md5 {
+            h.update(data);
+        }
+        if let Some(h) = &mut self.sha1 {
+            h.update(data);
+        }
+        if let Some(h) = &mut self.sha256 {
+            h.update(data);
+        }
+
+        let total_bytes = self
+            .state
+            .total_bytes
+            .fetch_add(data.len() as u64, Ordering::Relaxed)
+            + data.len() as u64;
+        if let Some(progress) = &self.state.progress {
+            progress(&UploadProgress {
+                filename: self.filename.clone(),
+                file_bytes: self.size,
+                file_size: self.checksums.size,
+                total_bytes,
+                total_size: self.state.total_size,
+            });
+        }
+    }
+
+    fn verify(&mut self) -> Result<(), ChecksumError> {
+        let mismatch = |kind, expected: &str, actual: String| {
+            if expected.eq_ignore_ascii_case(&actual) {
+                Ok(())
+            } else {
+                Err(ChecksumError {
+                    filename: self.filename.clone(),
+                    kind,
+                    expected: expected.to_owned(),
+                    actual,
+                })
+            }
+        };
+
+        if let Some(size) = self.checksums.size {
+            mismatch("size", &size.to_string(), self.size.to_string())?;
+        }
+        if let (Some(expected), Some(h)) = (&self.checksums.md5, self.md5.take()) {
+            mismatch("md5", expected, hex_digest(h))?;
+        }
+        if let (Some(expected), Some(h)) = (&self.checksums.sha1, self.sha1.take()) {
+            mismatch("sha1", expected, hex_digest(h))?;
+        }
+        if let (Some(expected), Some(h)) = (&self.checksums.sha256, self.sha256.take()) {
+            mismatch("sha256", expected, hex_digest(h))?;
+        }
+        Ok(())
+    }
+}
+
+fn hex_digest<H: Digest>(hasher: H) -> String {
+    base16ct::lower::encode_string(&hasher.finalize())
+}
+
+impl AsyncRead for UploadReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        let start = buf.filled().len();
+        ready!(self.contents.as_mut().poll_read(cx, buf))?;
+        let data = &buf.filled()[start..];
+        if !data.is_empty() {
+            self.update(data);
+        } else if let Err(e) = self.verify() {
+            // Remember the mismatch, as reqwest only reports a generic body
+            // error for the aborted request
+            *self.state.failure.lock().unwrap() = Some(e.clone());
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::InvalidData, e)));
+        }
+        Poll::Ready(Ok(()))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -70,14 +282,51 @@ impl FilesApiDirectory<'_> {
     }
 
     pub async fn upload(&self, upload: UploadFiles) -> Result<(), AptlyRestError> {
+        self.do_upload(upload, None).await
+    }
+
+    /// Upload files, calling `progress` every time a chunk of a file has
+    /// been sent. To get the progress reported elsewhere, send it on a
+    /// channel from the callback.
+    pub async fn upload_with_progress(
+        &self,
+        upload: UploadFiles,
+        progress: impl Fn(&UploadProgress) + Send + Sync + 'static,
+    ) -> Result<(), AptlyRestError> {
+        self.do_upload(upload, Some(Arc::new(progress))).await
+    }
+
+    async fn do_upload(
+        &self,
+        upload: UploadFiles,
+        progress: Option<ProgressCallback>,
+    ) -> Result<(), AptlyRestError> {
+        let state = Arc::new(UploadState {
+            total_bytes: AtomicU64::new(0),
+            total_size: upload.files.iter().map(|f| f.checksums.size).sum(),
+            progress,
+            failure: Mutex::new(None),
+        });
+
         let form = upload
-            .parts
+            .files
             .into_iter()
-            .fold(reqwest::multipart::Form::new(), |form, part| {
-                form.part("file", part)
+            .fold(reqwest::multipart::Form::new(), |form, file| {
+                let filename = file.filename.clone();
+                let reader = UploadReader::new(file, state.clone());
+                let body = reqwest::Body::wrap_stream(FramedRead::new(reader, BytesCodec::new()));
+                form.part(
+                    "file",
+                    reqwest::multipart::Part::stream(body).file_name(filename),
+                )
             });
         let req = self.files.aptly.client.post(self.url()).multipart(form);
-        self.files.aptly.send_request(req).await?;
+        let result = self.files.aptly.send_request(req).await;
+
+        if let Some(failure) = state.failure.lock().unwrap().take() {
+            return Err(failure.into());
+        }
+        result?;
 
         Ok(())
     }
@@ -0,0 +1,70 @@
+use serde::Serialize;
+
+use crate::{version::Capability, AptlyRestError};
+
+/// Where to get the public keys to import from
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    /// Fetch the keys with the given ids from a keyserver, or from aptly's
+    /// default keyserver if `None`
+    Keyserver {
+        keyserver: Option<String>,
+        key_ids: Vec<String>,
+    },
+    /// ASCII armored public keys
+    Armored(String),
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct ImportKeyOptions {
+    /// Keyring to add the keys to, aptly defaults to `trustedkeys.gpg`
+    pub keyring: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct ImportKeyRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keyserver: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gpg_key_armor: Option<&'a str>,
+    #[serde(rename = "GpgKeyID", skip_serializing_if = "Option::is_none")]
+    gpg_key_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keyring: Option<&'a str>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GpgApi<'a> {
+    pub(crate) aptly: &'a crate::AptlyRest,
+}
+
+impl GpgApi<'_> {
+    /// Import public keys into a keyring on the server, returning the output
+    /// of gpg
+    pub async fn import_key(
+        &self,
+        source: &KeySource,
+        options: &ImportKeyOptions,
+    ) -> Result<String, AptlyRestError> {
+        self.aptly.require(Capability::GpgKeys).await?;
+
+        let mut request = ImportKeyRequest {
+            keyserver: None,
+            gpg_key_armor: None,
+            gpg_key_id: None,
+            keyring: options.keyring.as_deref(),
+        };
+        match source {
+            KeySource::Keyserver { keyserver, key_ids } => {
+                request.keyserver = keyserver.as_deref();
+                request.gpg_key_id = Some(key_ids.join(" "));
+            }
+            KeySource::Armored(armor) => request.gpg_key_armor = Some(armor),
+        }
+
+        self.aptly
+            .post_body(self.aptly.url(&["api", "gpg", "key"]), &request)
+            .await
+    }
+}
@@ -0,0 +1,391 @@
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DefaultOnNull, NoneAsEmptyString};
+
+use crate::{api::repos::Package, key::AptlyKey, AptlyRestError};
+
+#[derive(Debug, Clone)]
+pub struct MirrorApi<'a> {
+    pub(crate) aptly: &'a crate::AptlyRest,
+    pub(crate) name: String,
+}
+
+impl MirrorApi<'_> {
+    fn url(&self) -> Url {
+        self.aptly.url(&["api", "mirrors", &self.name])
+    }
+
+    pub fn packages(&self) -> MirrorApiPackages<'_> {
+        MirrorApiPackages { mirror: self }
+    }
+
+    pub async fn get(&self) -> Result<Mirror, AptlyRestError> {
+        self.aptly.get(self.url()).await
+    }
+
+    /// Change the settings of the mirror.
+    ///
+    /// Aptly applies mirror edits as part of an update, so this will also
+    /// fetch the mirror contents with the new settings.
+    pub async fn edit(&self, options: &EditOptions) -> Result<Mirror, AptlyRestError> {
+        self.aptly.put_body(self.url(), options).await
+    }
+
+    /// Fetch the latest contents of the mirror from the remote archive.
+    pub async fn update(&self, options: &UpdateOptions) -> Result<Mirror, AptlyRestError> {
+        self.aptly.put_body(self.url(), options).await
+    }
+
+    pub async fn snapshot(
+        &self,
+        name: &str,
+        options: &SnapshotOptions,
+    ) -> Result<crate::Snapshot, AptlyRestError> {
+        #[derive(Debug, Clone, Serialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct SnapshotRequest<'a> {
+            name: &'a str,
+            #[serde(flatten)]
+            options: &'a SnapshotOptions,
+        }
+
+        self.aptly
+            .post_body(
+                self.aptly.url(&["api", "mirrors", &self.name, "snapshots"]),
+                &SnapshotRequest { name, options },
+            )
+            .await
+    }
+
+    pub async fn delete(&self, options: &DeleteOptions) -> Result<(), AptlyRestError> {
+        let mut url = self.url();
+
+        {
+            let mut pairs = url.query_pairs_mut();
+            if options.force {
+                pairs.append_pair("force", "1");
+            }
+        }
+
+        self.aptly
+            .send_request(self.aptly.client.delete(url))
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MirrorApiPackages<'a> {
+    mirror: &'a MirrorApi<'a>,
+}
+
+impl MirrorApiPackages<'_> {
+    fn url(&self, query: Option<&str>, with_deps: bool, detailed: bool) -> Url {
+        let mut url = self
+            .mirror
+            .aptly
+            .url(&["api", "mirrors", &self.mirror.name, "packages"]);
+
+        let mut pairs = url.query_pairs_mut();
+        if let Some(query) = query {
+            pairs.append_pair("q", query);
+            if with_deps {
+                pairs.append_pair("withDeps", "1");
+            }
+        }
+
+        if detailed {
+            pairs.append_pair("format", "details");
+        }
+
+        drop(pairs);
+        url
+    }
+
+    async fn do_list(
+        &self,
+        query: Option<&str>,
+        with_deps: bool,
+    ) -> Result<Vec<AptlyKey>, AptlyRestError> {
+        let url = self.url(query, with_deps, false);
+        self.mirror.aptly.get(url).await
+    }
+
+    async fn do_detailed(
+        &self,
+        query: Option<&str>,
+        with_deps: bool,
+    ) -> Result<Vec<Package>, AptlyRestError> {
+        let url = self.url(query, with_deps, true);
+        self.mirror.aptly.get(url).await
+    }
+
+    pub async fn list(&self) -> Result<Vec<AptlyKey>, AptlyRestError> {
+        self.do_list(None, false).await
+    }
+
+    pub async fn detailed(&self) -> Result<Vec<Package>, AptlyRestError> {
+        self.do_detailed(None, false).await
+    }
+
+    /// Packages matching `query`, a [crate::query::Query] or a raw query
+    /// string
+    pub fn query(&self, query: impl ToString, with_deps: bool) -> MirrorApiPackagesQuery<'_> {
+        MirrorApiPackagesQuery {
+            parent: self,
+            query: query.to_string(),
+            with_deps,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MirrorApiPackagesQuery<'a> {
+    parent: &'a MirrorApiPackages<'a>,
+    query: String,
+    with_deps: bool,
+}
+
+impl MirrorApiPackagesQuery<'_> {
+    pub async fn list(&self) -> Result<Vec<AptlyKey>, AptlyRestError> {
+        self.parent.do_list(Some(&self.query), self.with_deps).await
+    }
+
+    pub async fn detailed(&self) -> Result<Vec<Package>, AptlyRestError> {
+        self.parent
+            .do_detailed(Some(&self.query), self.with_deps)
+            .await
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Mirror {
+    #[serde(rename = "UUID", default)]
+    uuid: String,
+    name: String,
+    archive_root: String,
+    distribution: String,
+    #[serde_as(as = "DefaultOnNull")]
+    #[serde(default)]
+    components: Vec<String>,
+    #[serde_as(as = "DefaultOnNull")]
+    #[serde(default)]
+    architectures: Vec<String>,
+    #[serde(default)]
+    download_sources: bool,
+    #[serde(default)]
+    download_udebs: bool,
+    #[serde(default)]
+    download_installer: bool,
+    #[serde_as(as = "NoneAsEmptyString")]
+    #[serde(default)]
+    filter: Option<String>,
+    #[serde(default)]
+    filter_with_deps: bool,
+    #[serde(default)]
+    skip_component_check: bool,
+    #[serde(default)]
+    skip_architecture_check: bool,
+    #[serde(default)]
+    last_download_date: Option<String>,
+    #[serde(default)]
+    status: u32,
+}
+
+impl Mirror {
+    pub fn uuid(&self) -> &str {
+        &self.uuid
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn archive_root(&self) -> &str {
+        &self.archive_root
+    }
+
+    pub fn distribution(&self) -> &str {
+        &self.distribution
+    }
+
+    pub fn components(&self) -> &[String] {
+        &self.components
+    }
+
+    pub fn architectures(&self) -> &[String] {
+        &self.architectures
+    }
+
+    pub fn download_sources(&self) -> bool {
+        self.download_sources
+    }
+
+    pub fn download_udebs(&self) -> bool {
+        self.download_udebs
+    }
+
+    pub fn download_installer(&self) -> bool {
+        self.download_installer
+    }
+
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    pub fn filter_with_deps(&self) -> bool {
+        self.filter_with_deps
+    }
+
+    pub fn skip_component_check(&self) -> bool {
+        self.skip_component_check
+    }
+
+    pub fn skip_architecture_check(&self) -> bool {
+        self.skip_architecture_check
+    }
+
+    pub fn last_download_date(&self) -> Option<&str> {
+        self.last_download_date.as_deref()
+    }
+
+    /// true if aptly is currently downloading the mirror
+    pub fn is_updating(&self) -> bool {
+        self.status != 0
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distribution: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub architectures: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keyrings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    pub filter_with_deps: bool,
+    pub download_sources: bool,
+    pub download_udebs: bool,
+    pub download_installer: bool,
+    pub skip_component_check: bool,
+    pub skip_architecture_check: bool,
+    pub ignore_signatures: bool,
+}
+
+/// Mirror settings to change; fields left as `None` keep their current value.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EditOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(rename = "ArchiveURL", skip_serializing_if = "Option::is_none")]
+    pub archive_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub architectures: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter_with_deps: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_sources: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_udebs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_component_check: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct UpdateOptions {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub keyrings: Vec<String>,
+    pub force_update: bool,
+    pub ignore_checksums: bool,
+    pub ignore_signatures: bool,
+    pub skip_existing_packages: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tries: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SnapshotOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct DeleteOptions {
+    pub force: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deserialize_mirror() {
+        let m: Mirror = serde_json::from_value(json!({
+            "UUID": "2cb5985a-a23f-4a1f-8eb6-d5409193b4eb",
+            "Name": "bookworm-main",
+            "ArchiveRoot": "http://deb.debian.org/debian/",
+            "Distribution": "bookworm",
+            "Components": ["main"],
+            "Architectures": ["amd64", "arm64"],
+            "Meta": {
+                "Codename": "bookworm",
+                "Origin": "Debian"
+            },
+            "LastDownloadDate": "2024-11-19T09:39:02.123456789Z",
+            "Filter": "",
+            "Status": 0,
+            "WorkerPID": 0,
+            "FilterWithDeps": false,
+            "SkipComponentCheck": false,
+            "SkipArchitectureCheck": false,
+            "DownloadSources": true,
+            "DownloadUdebs": false,
+            "DownloadInstaller": false
+        }))
+        .unwrap();
+
+        assert_eq!("bookworm-main", m.name());
+        assert_eq!(&["main"], m.components());
+        assert_eq!(None, m.filter());
+        assert!(m.download_sources());
+        assert!(!m.is_updating());
+    }
+
+    #[test]
+    fn serialize_snapshot_options() {
+        let json = serde_json::to_value(SnapshotOptions::default()).unwrap();
+        assert_eq!(json!({}), json);
+    }
+
+    #[test]
+    fn deserialize_mirror_null_lists() {
+        let m: Mirror = serde_json::from_value(json!({
+            "Name": "empty",
+            "ArchiveRoot": "http://deb.debian.org/debian/",
+            "Distribution": "bookworm",
+            "Components": null,
+            "Architectures": null,
+            "Status": 1
+        }))
+        .unwrap();
+
+        assert!(m.components().is_empty());
+        assert!(m.architectures().is_empty());
+        assert!(m.is_updating());
+    }
+}
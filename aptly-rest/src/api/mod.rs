@@ -1,5 +1,9 @@
 pub mod files;
+pub mod gpg;
+pub mod mirrors;
 pub mod packages;
 pub mod publish;
 pub mod repos;
 pub mod snapshots;
+pub mod storage;
+pub mod tasks;
@@ -1,6 +1,10 @@
+use std::collections::BTreeMap;
+
+use debian_packaging::{control::ControlParagraph, dependency::DependencyList, error::DebianError};
+use futures::stream::BoxStream;
 use reqwest::Url;
-use serde::{de, Deserialize, Deserializer};
-use serde_with::{serde_as, DisplayFromStr, NoneAsEmptyString};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::{serde_as, skip_serializing_none, DisplayFromStr, NoneAsEmptyString};
 
 use crate::{key::AptlyKey, AptlyRestError};
 
@@ -47,6 +51,24 @@ impl PackagesApi<'_> {
         self.aptly.get(url).await
     }
 
+    fn do_list_stream(
+        &self,
+        query: Option<&str>,
+        with_deps: bool,
+    ) -> BoxStream<'static, Result<AptlyKey, AptlyRestError>> {
+        let url = self.url(query, with_deps, false);
+        self.aptly.get_stream(url)
+    }
+
+    fn do_detailed_stream(
+        &self,
+        query: Option<&str>,
+        with_deps: bool,
+    ) -> BoxStream<'static, Result<Package, AptlyRestError>> {
+        let url = self.url(query, with_deps, true);
+        self.aptly.get_stream(url)
+    }
+
     pub async fn list(&self) -> Result<Vec<AptlyKey>, AptlyRestError> {
         self.do_list(None, false).await
     }
@@ -55,10 +77,31 @@ impl PackagesApi<'_> {
         self.do_detailed(None, false).await
     }
 
-    pub fn query(&self, query: String, with_deps: bool) -> PackagesApiQuery<'_> {
+    /// Like [Self::list], but yields the keys while the response is still
+    /// being received
+    pub fn list_stream(&self) -> BoxStream<'static, Result<AptlyKey, AptlyRestError>> {
+        self.do_list_stream(None, false)
+    }
+
+    /// Like [Self::detailed], but deserializes the packages one at a time
+    /// while the response is being received, rather than buffering all of
+    /// them in memory
+    pub fn detailed_stream(&self) -> BoxStream<'static, Result<Package, AptlyRestError>> {
+        self.do_detailed_stream(None, false)
+    }
+
+    /// The full record of a single package in the pool
+    pub async fn get(&self, key: &AptlyKey) -> Result<Package, AptlyRestError> {
+        let url = self.aptly.url(&["api", "packages", &key.to_string()]);
+        self.aptly.get(url).await
+    }
+
+    /// Packages matching `query`, a [crate::query::Query] or a raw query
+    /// string
+    pub fn query(&self, query: impl ToString, with_deps: bool) -> PackagesApiQuery<'_> {
         PackagesApiQuery {
             parent: self,
-            query,
+            query: query.to_string(),
             with_deps,
         }
     }
@@ -81,6 +124,16 @@ impl PackagesApiQuery<'_> {
             .do_detailed(Some(&self.query), self.with_deps)
             .await
     }
+
+    pub fn list_stream(&self) -> BoxStream<'static, Result<AptlyKey, AptlyRestError>> {
+        self.parent
+            .do_list_stream(Some(&self.query), self.with_deps)
+    }
+
+    pub fn detailed_stream(&self) -> BoxStream<'static, Result<Package, AptlyRestError>> {
+        self.parent
+            .do_detailed_stream(Some(&self.query), self.with_deps)
+    }
 }
 
 #[serde_as]
@@ -164,8 +217,91 @@ where
         .collect()
 }
 
+/// Read the remaining fields as strings; aptly only returns strings for
+/// control fields, but anything else shouldn't make the package unreadable
+fn deserialize_extra_fields<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let fields = BTreeMap::<String, serde_json::Value>::deserialize(deserializer)?;
+    Ok(fields
+        .into_iter()
+        .filter_map(|(name, value)| match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some((name, s)),
+            v => Some((name, v.to_string())),
+        })
+        .collect())
+}
+
+/// Fields aptly adds to package metadata that aren't part of the control
+/// paragraph
+const APTLY_FIELDS: [&str; 2] = ["ShortKey", "FilesHash"];
+
+/// Fields holding one entry per line, which start on the line after the
+/// field name
+const LIST_FIELDS: [&str; 5] = [
+    "Files",
+    "Checksums-Sha1",
+    "Checksums-Sha256",
+    "Checksums-Sha512",
+    "Package-List",
+];
+
+/// Builds a control paragraph out of aptly's representation of the fields
+#[derive(Default)]
+struct ParagraphBuilder(ControlParagraph<'static>);
+
+impl ParagraphBuilder {
+    fn field(&mut self, name: &str, value: Option<&str>) {
+        let Some(value) = value else {
+            return;
+        };
+
+        let value = if LIST_FIELDS.contains(&name) {
+            value
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .fold(String::new(), |value, line| value + "\n" + line)
+        } else {
+            value.trim().to_owned()
+        };
+        self.0
+            .set_field_from_string(name.to_owned().into(), value.into());
+    }
+
+    fn extra_fields(&mut self, fields: &BTreeMap<String, String>) {
+        for (name, value) in fields {
+            if !APTLY_FIELDS.contains(&name.as_str()) {
+                self.field(name, Some(value));
+            }
+        }
+    }
+}
+
+fn dependency_list(value: &Option<String>) -> Option<Result<DependencyList, DebianError>> {
+    value.as_deref().map(DependencyList::parse)
+}
+
+fn serialize_files<S>(files: &[File], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&format_files(files))
+}
+
+fn format_files(files: &[File]) -> String {
+    files
+        .iter()
+        .map(|f| format!(" {} {} {}\n", f.checksum, f.size, f.filename))
+        .collect()
+}
+
+/// A source package. Relationship fields are parsed on access, so a single
+/// malformed field doesn't make the whole package list unreadable.
 #[serde_as]
-#[derive(Deserialize, Clone, Debug)]
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct Source {
     package: String,
@@ -173,10 +309,36 @@ pub struct Source {
     #[serde_as(as = "DisplayFromStr")]
     key: AptlyKey,
     architecture: String,
-    #[serde(deserialize_with = "deserialize_files", rename = "Checksums-Sha256")]
+    #[serde(
+        deserialize_with = "deserialize_files",
+        serialize_with = "serialize_files",
+        rename = "Checksums-Sha256"
+    )]
     sha256_files: Vec<File>,
-    #[serde(flatten)]
-    _unparsed: serde_json::Value,
+    binary: Option<String>,
+    maintainer: Option<String>,
+    uploaders: Option<String>,
+    section: Option<String>,
+    priority: Option<String>,
+    homepage: Option<String>,
+    format: Option<String>,
+    #[serde(rename = "Standards-Version")]
+    standards_version: Option<String>,
+    directory: Option<String>,
+    #[serde(rename = "Build-Depends")]
+    build_depends: Option<String>,
+    #[serde(rename = "Build-Depends-Indep")]
+    build_depends_indep: Option<String>,
+    #[serde(rename = "Build-Depends-Arch")]
+    build_depends_arch: Option<String>,
+    #[serde(rename = "Build-Conflicts")]
+    build_conflicts: Option<String>,
+    #[serde(rename = "Build-Conflicts-Indep")]
+    build_conflicts_indep: Option<String>,
+    #[serde(rename = "Build-Conflicts-Arch")]
+    build_conflicts_arch: Option<String>,
+    #[serde(flatten, deserialize_with = "deserialize_extra_fields")]
+    extra_fields: BTreeMap<String, String>,
 }
 
 impl Source {
@@ -199,10 +361,113 @@ impl Source {
     pub fn sha256_files(&self) -> &[File] {
         &self.sha256_files
     }
+
+    /// Names of the binary packages built from this source
+    pub fn binaries(&self) -> impl Iterator<Item = &str> {
+        self.binary
+            .iter()
+            .flat_map(|b| b.split(','))
+            .map(str::trim)
+            .filter(|b| !b.is_empty())
+    }
+
+    pub fn maintainer(&self) -> Option<&str> {
+        self.maintainer.as_deref()
+    }
+
+    pub fn uploaders(&self) -> Option<&str> {
+        self.uploaders.as_deref()
+    }
+
+    pub fn section(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+
+    pub fn priority(&self) -> Option<&str> {
+        self.priority.as_deref()
+    }
+
+    pub fn homepage(&self) -> Option<&str> {
+        self.homepage.as_deref()
+    }
+
+    pub fn format(&self) -> Option<&str> {
+        self.format.as_deref()
+    }
+
+    pub fn standards_version(&self) -> Option<&str> {
+        self.standards_version.as_deref()
+    }
+
+    /// Location of the source package files in the pool
+    pub fn directory(&self) -> Option<&str> {
+        self.directory.as_deref()
+    }
+
+    pub fn build_depends(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.build_depends)
+    }
+
+    pub fn build_depends_indep(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.build_depends_indep)
+    }
+
+    pub fn build_depends_arch(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.build_depends_arch)
+    }
+
+    pub fn build_conflicts(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.build_conflicts)
+    }
+
+    pub fn build_conflicts_indep(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.build_conflicts_indep)
+    }
+
+    pub fn build_conflicts_arch(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.build_conflicts_arch)
+    }
+
+    /// Any other field of the package, by its control field name
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.extra_fields.get(name).map(String::as_str)
+    }
+
+    /// The package as a paragraph of a `Sources` index
+    pub fn control_paragraph(&self) -> ControlParagraph<'static> {
+        let mut p = ParagraphBuilder::default();
+        p.field("Package", Some(&self.package));
+        p.field("Binary", self.binary.as_deref());
+        p.field("Version", Some(&self.version));
+        p.field("Maintainer", self.maintainer.as_deref());
+        p.field("Uploaders", self.uploaders.as_deref());
+        p.field("Build-Depends", self.build_depends.as_deref());
+        p.field("Build-Depends-Indep", self.build_depends_indep.as_deref());
+        p.field("Build-Depends-Arch", self.build_depends_arch.as_deref());
+        p.field("Build-Conflicts", self.build_conflicts.as_deref());
+        p.field(
+            "Build-Conflicts-Indep",
+            self.build_conflicts_indep.as_deref(),
+        );
+        p.field("Build-Conflicts-Arch", self.build_conflicts_arch.as_deref());
+        p.field("Architecture", Some(&self.architecture));
+        p.field("Standards-Version", self.standards_version.as_deref());
+        p.field("Format", self.format.as_deref());
+        p.field("Checksums-Sha256", Some(&format_files(&self.sha256_files)));
+        p.field("Homepage", self.homepage.as_deref());
+        p.field("Directory", self.directory.as_deref());
+        p.field("Priority", self.priority.as_deref());
+        p.field("Section", self.section.as_deref());
+        p.extra_fields(&self.extra_fields);
+        p.0
+    }
 }
 
+/// A binary package. Relationship fields are parsed on access, so a single
+/// malformed field doesn't make the whole package list unreadable.
 #[serde_as]
-#[derive(Deserialize, Clone, Debug)]
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct Binary {
     package: String,
@@ -213,8 +478,33 @@ pub struct Binary {
     key: AptlyKey,
     #[serde(rename = "SHA256")]
     sha256: String,
-    #[serde(flatten)]
-    _unparsed: serde_json::Value,
+    source: Option<String>,
+    maintainer: Option<String>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(rename = "Installed-Size")]
+    installed_size: Option<u64>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    size: Option<u64>,
+    section: Option<String>,
+    priority: Option<String>,
+    homepage: Option<String>,
+    description: Option<String>,
+    #[serde(rename = "Multi-Arch")]
+    multi_arch: Option<String>,
+    #[serde(rename = "Pre-Depends")]
+    pre_depends: Option<String>,
+    depends: Option<String>,
+    recommends: Option<String>,
+    suggests: Option<String>,
+    enhances: Option<String>,
+    breaks: Option<String>,
+    conflicts: Option<String>,
+    provides: Option<String>,
+    replaces: Option<String>,
+    #[serde(rename = "Built-Using")]
+    built_using: Option<String>,
+    #[serde(flatten, deserialize_with = "deserialize_extra_fields")]
+    extra_fields: BTreeMap<String, String>,
 }
 
 impl Binary {
@@ -241,9 +531,136 @@ impl Binary {
     pub fn sha256(&self) -> &str {
         self.sha256.as_ref()
     }
+
+    /// Name of the source package, if it differs from the binary package
+    pub fn source_package(&self) -> Option<&str> {
+        self.source
+            .as_deref()
+            .map(|s| s.split_once(' ').map_or(s, |(name, _)| name))
+    }
+
+    /// Version of the source package, if it differs from the binary version
+    pub fn source_version(&self) -> Option<&str> {
+        let (_, version) = self.source.as_deref()?.split_once(' ')?;
+        Some(version.trim_matches(|c| c == '(' || c == ')'))
+    }
+
+    pub fn maintainer(&self) -> Option<&str> {
+        self.maintainer.as_deref()
+    }
+
+    /// Installed size in KiB
+    pub fn installed_size(&self) -> Option<u64> {
+        self.installed_size
+    }
+
+    /// Size of the `.deb` in bytes
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn section(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+
+    pub fn priority(&self) -> Option<&str> {
+        self.priority.as_deref()
+    }
+
+    pub fn homepage(&self) -> Option<&str> {
+        self.homepage.as_deref()
+    }
+
+    /// The synopsis followed by the extended description, if any
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref().map(str::trim)
+    }
+
+    pub fn multi_arch(&self) -> Option<&str> {
+        self.multi_arch.as_deref()
+    }
+
+    pub fn pre_depends(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.pre_depends)
+    }
+
+    pub fn depends(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.depends)
+    }
+
+    pub fn recommends(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.recommends)
+    }
+
+    pub fn suggests(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.suggests)
+    }
+
+    pub fn enhances(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.enhances)
+    }
+
+    pub fn breaks(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.breaks)
+    }
+
+    pub fn conflicts(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.conflicts)
+    }
+
+    pub fn provides(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.provides)
+    }
+
+    pub fn replaces(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.replaces)
+    }
+
+    pub fn built_using(&self) -> Option<Result<DependencyList, DebianError>> {
+        dependency_list(&self.built_using)
+    }
+
+    /// Any other field of the package, by its control field name
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.extra_fields.get(name).map(String::as_str)
+    }
+
+    /// The package as a paragraph of a `Packages` index
+    pub fn control_paragraph(&self) -> ControlParagraph<'static> {
+        let installed_size = self.installed_size.map(|s| s.to_string());
+        let size = self.size.map(|s| s.to_string());
+
+        let mut p = ParagraphBuilder::default();
+        p.field("Package", Some(&self.package));
+        p.field("Source", self.source.as_deref());
+        p.field("Version", Some(&self.version));
+        p.field("Installed-Size", installed_size.as_deref());
+        p.field("Maintainer", self.maintainer.as_deref());
+        p.field("Architecture", Some(&self.architecture));
+        p.field("Multi-Arch", self.multi_arch.as_deref());
+        p.field("Replaces", self.replaces.as_deref());
+        p.field("Provides", self.provides.as_deref());
+        p.field("Depends", self.depends.as_deref());
+        p.field("Pre-Depends", self.pre_depends.as_deref());
+        p.field("Recommends", self.recommends.as_deref());
+        p.field("Suggests", self.suggests.as_deref());
+        p.field("Enhances", self.enhances.as_deref());
+        p.field("Conflicts", self.conflicts.as_deref());
+        p.field("Breaks", self.breaks.as_deref());
+        p.field("Built-Using", self.built_using.as_deref());
+        p.field("Description", self.description.as_deref());
+        p.field("Homepage", self.homepage.as_deref());
+        p.field("Section", self.section.as_deref());
+        p.field("Priority", self.priority.as_deref());
+        p.field("Filename", Some(&self.filename));
+        p.field("Size", size.as_deref());
+        p.field("SHA256", Some(&self.sha256));
+        p.extra_fields(&self.extra_fields);
+        p.0
+    }
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(untagged)]
 pub enum Package {
     Binary(Binary),
@@ -282,6 +699,13 @@ impl Package {
     pub fn is_source(&self) -> bool {
         matches!(self, Package::Source(_))
     }
+
+    pub fn control_paragraph(&self) -> ControlParagraph<'static> {
+        match self {
+            Package::Binary(b) => b.control_paragraph(),
+            Package::Source(s) => s.control_paragraph(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -321,6 +745,84 @@ mod test {
         .unwrap();
 
         assert_eq!("rustc", v.package());
+        let Package::Binary(binary) = &v else {
+            panic!("Expected a binary package");
+        };
+        assert_eq!(Some(5720), binary.installed_size());
+        assert_eq!(Some("rust"), binary.section());
+        assert_eq!(
+            Some("Rust systems programming language"),
+            binary.description()
+        );
+        assert_eq!(Some("allowed"), binary.multi_arch());
+        assert_eq!(
+            Some("0302b014f85fc6a49418fae4ee34ea71"),
+            binary.field("MD5sum")
+        );
+
+        let paragraph = v.control_paragraph();
+        assert_eq!(Some("rustc"), paragraph.field_str("Package"));
+        assert_eq!(Some("5720"), paragraph.field_str("Installed-Size"));
+        assert_eq!(
+            Some("cargo (>= 0.49.0~~), cargo (<< 0.50.0~~), rust-gdb | rust-lldb"),
+            paragraph.field_str("Recommends")
+        );
+        assert_eq!(
+            Some("Rust systems programming language"),
+            paragraph.field_str("Description")
+        );
+        assert!(!paragraph.has_field("ShortKey"));
+    }
+
+    #[test]
+    fn binary_relationships() {
+        let v: Binary = serde_json::from_value(json!({
+            "Architecture": "amd64",
+            "Depends": "libc6 (>= 2.14), rust-gdb | rust-lldb",
+            "Filename": "rustc_1.48.0+dfsg1-2_amd64.deb",
+            "Key": "Pamd64 rustc 1.48.0+dfsg1-2 87415bdc9ef60793",
+            "Package": "rustc",
+            "SHA256": "3cc857f1d9d5970d5d8ced830efb054520f820a4a496b6e55d68dbae19270137",
+            "Source": "rust (1.48.0+dfsg1-1)",
+            "Version": "1.48.0+dfsg1-2"
+        }))
+        .unwrap();
+
+        assert_eq!(Some("rust"), v.source_package());
+        assert_eq!(Some("1.48.0+dfsg1-1"), v.source_version());
+        assert!(v.pre_depends().is_none());
+
+        let depends = v.depends().unwrap().unwrap();
+        let names: Vec<Vec<&str>> = depends
+            .requirements()
+            .map(|variants| variants.iter().map(|d| d.package.as_str()).collect())
+            .collect();
+        assert_eq!(vec![vec!["libc6"], vec!["rust-gdb", "rust-lldb"]], names);
+    }
+
+    #[test]
+    fn non_string_extra_fields() {
+        let v: Package = serde_json::from_value(json!({
+            "Architecture": "amd64",
+            "Filename": "hello_2.10-3_amd64.deb",
+            "Key": "Pamd64 hello 2.10-3 1a2b3c4d5e6f7a8b",
+            "Package": "hello",
+            "SHA256": "0123456789abcdef",
+            "Version": "2.10-3",
+            "Task": ["standard", "desktop"],
+            "Phased-Update-Percentage": 10,
+            "Important": true,
+            "Unknown": null
+        }))
+        .unwrap();
+
+        let Package::Binary(binary) = &v else {
+            panic!("Expected a binary package");
+        };
+        assert_eq!(Some("10"), binary.field("Phased-Update-Percentage"));
+        assert_eq!(Some("true"), binary.field("Important"));
+        assert_eq!(Some(r#"["standard","desktop"]"#), binary.field("Task"));
+        assert_eq!(None, binary.field("Unknown"));
     }
 
     #[test]
@@ -354,5 +856,33 @@ mod test {
         })).unwrap();
 
         assert_eq!("rustc", v.package());
+        let Package::Source(source) = &v else {
+            panic!("Expected a source package");
+        };
+        assert_eq!(
+            vec!["rustc", "libstd-rust-1.48", "libstd-rust-dev"],
+            source.binaries().take(3).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("3.0 (quilt)"), source.format());
+        assert_eq!(Some("pool/main/r/rustc"), source.directory());
+        assert_eq!(
+            Some("https://salsa.debian.org/rust-team/rust"),
+            source.field("Vcs-Browser")
+        );
+
+        let paragraph = v.control_paragraph();
+        assert_eq!(Some("any all"), paragraph.field_str("Architecture"));
+        assert_eq!(
+            Some("\n 7b4db2ce181dc3d8999388c7ea32ac1a992b699dbc70e4b6cd0b88831437c5ff 76924 rustc_1.48.0+dfsg1-2.debian.tar.xz\n 41994d5bd2b33e25b541b330173061e9748eca95144cb52074cd5c9277bb6468 2665 rustc_1.48.0+dfsg1-2.dsc\n f39dd5901feb713bc8876a042c3105bf654177878d8bcc71962c8dcc041af367 22048320 rustc_1.48.0+dfsg1.orig.tar.xz"),
+            paragraph.field_str("Checksums-Sha256")
+        );
+        assert!(paragraph
+            .field_str("Package-List")
+            .unwrap()
+            .starts_with("\n libstd-rust-1.48 deb libs optional arch=any\n"));
+
+        let json = serde_json::to_value(&v).unwrap();
+        assert_eq!(json["Checksums-Sha256"].as_str(), Some(" 7b4db2ce181dc3d8999388c7ea32ac1a992b699dbc70e4b6cd0b88831437c5ff 76924 rustc_1.48.0+dfsg1-2.debian.tar.xz\n 41994d5bd2b33e25b541b330173061e9748eca95144cb52074cd5c9277bb6468 2665 rustc_1.48.0+dfsg1-2.dsc\n f39dd5901feb713bc8876a042c3105bf654177878d8bcc71962c8dcc041af367 22048320 rustc_1.48.0+dfsg1.orig.tar.xz\n"));
+        assert_eq!(json["Standards-Version"].as_str(), Some("4.2.1"));
     }
 }
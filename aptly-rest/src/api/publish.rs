@@ -1,18 +1,26 @@
+use std::collections::BTreeMap;
+
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DeserializeAs, NoneAsEmptyString, SerializeAs};
+use serde_with::{serde_as, skip_serializing_none, DeserializeAs, DisplayFromStr, SerializeAs};
 
-use crate::AptlyRestError;
+use crate::{
+    api::tasks::TaskApi,
+    prefix::{PublishPrefix, StorageEndpoint},
+    version::Capability,
+    AptlyRestError,
+};
 
 #[derive(Debug, Clone)]
 pub struct PublishApi<'a> {
     pub(crate) aptly: &'a crate::AptlyRest,
-    pub(crate) prefix: String,
+    pub(crate) prefix: PublishPrefix,
 }
 
-impl PublishApi<'_> {
+impl<'a> PublishApi<'a> {
     fn url(&self) -> Url {
-        self.aptly.url(&["api", "publish", &self.prefix])
+        self.aptly
+            .url(&["api", "publish", &self.prefix.url_segment()])
     }
 
     pub fn distribution<S: Into<String>>(&self, distribution: S) -> DistributionApi<'_> {
@@ -28,16 +36,7 @@ impl PublishApi<'_> {
         sources: &[Source],
         options: &PublishOptions,
     ) -> Result<PublishedRepo, AptlyRestError> {
-        #[derive(Debug, Serialize)]
-        #[serde(rename_all = "PascalCase")]
-        struct PublishRequest<'options> {
-            #[serde(rename = "SourceKind")]
-            kind: SourceKind,
-            sources: &'options [Source],
-            #[serde(flatten)]
-            options: &'options PublishOptions,
-        }
-
+        require_multi_dist(self.aptly, options.multi_dist).await?;
         self.aptly
             .post_body(
                 self.url(),
@@ -49,6 +48,35 @@ impl PublishApi<'_> {
             )
             .await
     }
+
+    /// Like [PublishApi::publish], but run as an aptly background task; the
+    /// task's result is a [PublishedRepo].
+    pub async fn publish_async(
+        &self,
+        kind: SourceKind,
+        sources: &[Source],
+        options: &PublishOptions,
+    ) -> Result<TaskApi<'a>, AptlyRestError> {
+        require_multi_dist(self.aptly, options.multi_dist).await?;
+        let url = crate::AptlyRest::async_url(self.url());
+        self.aptly
+            .task_request(self.aptly.client.post(url).json(&PublishRequest {
+                kind,
+                sources,
+                options,
+            }))
+            .await
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PublishRequest<'options> {
+    #[serde(rename = "SourceKind")]
+    kind: SourceKind,
+    sources: &'options [Source],
+    #[serde(flatten)]
+    options: &'options PublishOptions,
 }
 
 #[derive(Debug, Clone)]
@@ -57,17 +85,70 @@ pub struct DistributionApi<'a> {
     pub(crate) distribution: String,
 }
 
-impl DistributionApi<'_> {
+impl<'a> DistributionApi<'a> {
     fn url(&self) -> Url {
-        self.publish
-            .aptly
-            .url(&["api", "publish", &self.publish.prefix, &self.distribution])
+        self.publish.aptly.url(&[
+            "api",
+            "publish",
+            &self.publish.prefix.url_segment(),
+            &self.distribution,
+        ])
     }
 
     pub async fn update(&self, options: &UpdateOptions) -> Result<PublishedRepo, AptlyRestError> {
+        require_multi_dist(self.publish.aptly, options.multi_dist == Some(true)).await?;
         self.publish.aptly.put_body(self.url(), options).await
     }
 
+    /// Like [DistributionApi::update], but run as an aptly background task;
+    /// the task's result is a [PublishedRepo].
+    pub async fn update_async(
+        &self,
+        options: &UpdateOptions,
+    ) -> Result<TaskApi<'a>, AptlyRestError> {
+        let aptly: &'a crate::AptlyRest = self.publish.aptly;
+        require_multi_dist(aptly, options.multi_dist == Some(true)).await?;
+        let url = crate::AptlyRest::async_url(self.url());
+        aptly
+            .task_request(aptly.client.put(url).json(options))
+            .await
+    }
+
+    /// Switch components of a snapshot publish to new snapshots in a single
+    /// update. Every component must already be published and every snapshot
+    /// must exist; a source without a component is only accepted if the
+    /// publish has a single component.
+    pub async fn switch(
+        &self,
+        snapshots: &[Source],
+        options: &UpdateOptions,
+    ) -> Result<PublishedRepo, AptlyRestError> {
+        let aptly = self.publish.aptly;
+        let published = aptly
+            .published()
+            .await?
+            .into_iter()
+            .find(|p| {
+                p.publish_prefix() == self.publish.prefix && p.distribution() == self.distribution
+            })
+            .ok_or_else(|| {
+                SwitchError::NotPublished(format!("{}/{}", self.publish.prefix, self.distribution))
+            })?;
+        let existing: Vec<_> = aptly
+            .snapshots()
+            .await?
+            .into_iter()
+            .map(|s| s.name().to_owned())
+            .collect();
+
+        let snapshots = switch_sources(&published, &existing, snapshots)?;
+        self.update(&UpdateOptions {
+            snapshots: Some(snapshots),
+            ..options.clone()
+        })
+        .await
+    }
+
     pub async fn delete(&self, options: &DeleteOptions) -> Result<(), AptlyRestError> {
         let mut url = self.url();
 
@@ -86,6 +167,83 @@ impl DistributionApi<'_> {
     }
 }
 
+async fn require_multi_dist(
+    aptly: &crate::AptlyRest,
+    multi_dist: bool,
+) -> Result<(), AptlyRestError> {
+    if multi_dist {
+        aptly.require(Capability::MultiDist).await?;
+    }
+    Ok(())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SwitchError {
+    #[error("No published repository at '{0}'")]
+    NotPublished(String),
+    #[error("Published repository at '{0}' is not published from snapshots")]
+    NotSnapshotPublish(String),
+    #[error("Component '{0}' is not part of the published repository")]
+    UnknownComponent(String),
+    #[error("Snapshot '{0}' needs a component; the publish has several")]
+    MissingComponent(String),
+    #[error("Component '{0}' given more than once")]
+    DuplicateComponent(String),
+    #[error("Snapshot '{0}' does not exist")]
+    UnknownSnapshot(String),
+}
+
+/// Check the sources to switch to against the current publish, filling in the
+/// component of sources that don't have one.
+fn switch_sources(
+    published: &PublishedRepo,
+    existing_snapshots: &[String],
+    snapshots: &[Source],
+) -> Result<Vec<Source>, SwitchError> {
+    let path = format!(
+        "{}/{}",
+        published.publish_prefix(),
+        published.distribution()
+    );
+    if published.source_kind() != SourceKind::Snapshot {
+        return Err(SwitchError::NotSnapshotPublish(path));
+    }
+
+    let components: Vec<_> = published
+        .sources()
+        .iter()
+        .filter_map(|s| s.component.as_deref())
+        .collect();
+
+    let mut resolved: Vec<Source> = Vec::with_capacity(snapshots.len());
+    for source in snapshots {
+        let component = match (&source.component, components.as_slice()) {
+            (Some(component), _) => component.as_str(),
+            (None, [only]) => only,
+            (None, _) => return Err(SwitchError::MissingComponent(source.name.clone())),
+        };
+        if !components.contains(&component) {
+            return Err(SwitchError::UnknownComponent(component.to_owned()));
+        }
+        if resolved
+            .iter()
+            .any(|s| s.component.as_deref() == Some(component))
+        {
+            return Err(SwitchError::DuplicateComponent(component.to_owned()));
+        }
+        if !existing_snapshots.contains(&source.name) {
+            return Err(SwitchError::UnknownSnapshot(source.name.clone()));
+        }
+
+        resolved.push(Source {
+            name: source.name.clone(),
+            component: Some(component.to_owned()),
+        });
+    }
+
+    Ok(resolved)
+}
+
 #[derive(Debug, Default, Clone, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct SigningOptions {
@@ -177,12 +335,12 @@ pub enum SourceKind {
 }
 
 #[serde_as]
+#[skip_serializing_none]
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct PublishedRepo {
-    #[serde(rename = "Storage")]
-    #[serde_as(as = "NoneAsEmptyString")]
-    storage_kind: Option<String>,
+    #[serde_as(as = "DisplayFromStr")]
+    storage: StorageEndpoint,
     prefix: String,
     distribution: String,
     source_kind: SourceKind,
@@ -195,17 +353,41 @@ pub struct PublishedRepo {
     #[serde_as(as = "YesNoBool")]
     but_automatic_upgrades: bool,
     acquire_by_hash: bool,
+    // Only returned by newer versions of aptly
+    suite: Option<String>,
+    codename: Option<String>,
+    skip_contents: Option<bool>,
+    skip_bz2: Option<bool>,
+    multi_dist: Option<bool>,
+    path: Option<String>,
+    signed_by: Option<String>,
+    /// Anything else aptly returns, so the publish round-trips unchanged
+    #[serde(flatten)]
+    extra_fields: BTreeMap<String, serde_json::Value>,
 }
 
 impl PublishedRepo {
-    pub fn storage_kind(&self) -> Option<&str> {
-        self.storage_kind.as_deref()
+    pub fn storage(&self) -> &StorageEndpoint {
+        &self.storage
     }
 
+    /// The storage as written by aptly e.g. `s3:bucket`, `None` for the local
+    /// storage
+    #[deprecated(note = "use `storage()` instead")]
+    pub fn storage_kind(&self) -> Option<String> {
+        (!self.storage.is_local()).then(|| self.storage.to_string())
+    }
+
+    /// The path of the publish within its storage
     pub fn prefix(&self) -> &str {
         &self.prefix
     }
 
+    /// The full prefix of the publish, including its storage
+    pub fn publish_prefix(&self) -> PublishPrefix {
+        PublishPrefix::with_storage(self.storage.clone(), &self.prefix)
+    }
+
     pub fn distribution(&self) -> &str {
         &self.distribution
     }
@@ -241,6 +423,44 @@ impl PublishedRepo {
     pub fn acquire_by_hash(&self) -> bool {
         self.acquire_by_hash
     }
+
+    /// The `Suite` of the Release file, if it differs from the distribution
+    pub fn suite(&self) -> Option<&str> {
+        self.suite.as_deref().filter(|s| !s.is_empty())
+    }
+
+    /// The `Codename` of the Release file, if it differs from the
+    /// distribution
+    pub fn codename(&self) -> Option<&str> {
+        self.codename.as_deref().filter(|s| !s.is_empty())
+    }
+
+    pub fn skip_contents(&self) -> bool {
+        self.skip_contents.unwrap_or_default()
+    }
+
+    pub fn skip_bz2(&self) -> bool {
+        self.skip_bz2.unwrap_or_default()
+    }
+
+    /// Whether the distribution has a pool directory of its own
+    pub fn multi_dist(&self) -> bool {
+        self.multi_dist.unwrap_or_default()
+    }
+
+    /// Path of the publish within its storage, including the distribution
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// The `Signed-By` of the Release file
+    pub fn signed_by(&self) -> Option<&str> {
+        self.signed_by.as_deref().filter(|s| !s.is_empty())
+    }
+
+    pub fn field(&self, name: &str) -> Option<&serde_json::Value> {
+        self.extra_fields.get(name)
+    }
 }
 
 #[serde_as]
@@ -266,8 +486,17 @@ pub struct PublishOptions {
     pub acquire_by_hash: bool,
     pub skip_contents: bool,
     pub skip_bz2: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suite: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub codename: Option<String>,
+    /// Give the distribution a pool directory of its own; needs
+    /// [Capability::MultiDist]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub multi_dist: bool,
 }
 
+#[serde_as]
 #[derive(Debug, Default, Clone, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct UpdateOptions {
@@ -279,9 +508,191 @@ pub struct UpdateOptions {
     pub acquire_by_hash: bool,
     pub skip_contents: bool,
     pub skip_bz2: bool,
+    /// Change whether upgrades are automatic for a `NotAutomatic`
+    /// distribution, leaving it unchanged if `None`
+    #[serde_as(as = "Option<YesNoBool>")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub but_automatic_upgrades: Option<bool>,
+    /// Change whether the distribution has a pool directory of its own,
+    /// leaving it unchanged if `None`; enabling it needs
+    /// [Capability::MultiDist]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multi_dist: Option<bool>,
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct DeleteOptions {
     pub force: bool,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn published(kind: &str, components: &[&str]) -> PublishedRepo {
+        let sources: Vec<_> = components
+            .iter()
+            .map(|c| json!({"Name": format!("{c}-old"), "Component": c}))
+            .collect();
+        serde_json::from_value(json!({
+            "Storage": "",
+            "Prefix": ".",
+            "Distribution": "bookworm",
+            "SourceKind": kind,
+            "Sources": sources,
+            "Architectures": ["amd64"],
+            "Label": "",
+            "Origin": "",
+            "NotAutomatic": "",
+            "ButAutomaticUpgrades": "",
+            "AcquireByHash": false
+        }))
+        .unwrap()
+    }
+
+    fn source(name: &str, component: Option<&str>) -> Source {
+        Source {
+            name: name.to_owned(),
+            component: component.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn published_prefix() {
+        let p = published("local", &["main"]);
+        assert!(p.storage().is_local());
+        assert_eq!(None, p.storage_kind());
+        assert_eq!(PublishPrefix::new("."), p.publish_prefix());
+
+        let mut json = serde_json::to_value(&p).unwrap();
+        assert_eq!("", json["Storage"]);
+        json["Storage"] = "s3:bucket".into();
+        json["Prefix"] = "debian".into();
+        let p: PublishedRepo = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            "s3:bucket:debian".parse::<PublishPrefix>().unwrap(),
+            p.publish_prefix()
+        );
+        assert_eq!(Some("s3:bucket"), p.storage_kind().as_deref());
+    }
+
+    #[test]
+    fn published_round_trip() {
+        // As returned by aptly 1.6
+        let json = json!({
+            "AcquireByHash": true,
+            "Architectures": ["amd64", "source"],
+            "ButAutomaticUpgrades": "yes",
+            "Codename": "trixie",
+            "Distribution": "testing",
+            "Label": "Debian",
+            "MultiDist": true,
+            "NotAutomatic": "yes",
+            "Origin": "Debian",
+            "Path": "s3:bucket:debian/dists/testing",
+            "Prefix": "debian",
+            "SignedBy": "",
+            "SkipBz2": true,
+            "SkipContents": false,
+            "SourceKind": "snapshot",
+            "Sources": [{"Component": "main", "Name": "main-20240101"}],
+            "Storage": "s3:bucket",
+            "Suite": "testing",
+            "UUID": "f1c9a4c3-5e8a-4b2f-9d7e-3a6b1c2d4e5f"
+        });
+        let p: PublishedRepo = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(Some("trixie"), p.codename());
+        assert_eq!(Some("testing"), p.suite());
+        assert!(p.multi_dist());
+        assert!(p.skip_bz2());
+        assert!(!p.skip_contents());
+        assert!(p.but_automatic_upgrades());
+        assert_eq!(Some("s3:bucket:debian/dists/testing"), p.path());
+        assert_eq!(None, p.signed_by());
+        assert_eq!(
+            Some(&json!("f1c9a4c3-5e8a-4b2f-9d7e-3a6b1c2d4e5f")),
+            p.field("UUID")
+        );
+        assert_eq!(json, serde_json::to_value(&p).unwrap());
+
+        // Older versions leave the new fields out, and so should we
+        let p = published("local", &["main"]);
+        assert_eq!(None, p.suite());
+        assert!(!p.multi_dist());
+        let json = serde_json::to_value(&p).unwrap();
+        assert!(json.get("MultiDist").is_none());
+        assert!(json.get("Suite").is_none());
+    }
+
+    #[test]
+    fn options() {
+        let json = serde_json::to_value(PublishOptions {
+            suite: Some("stable".to_owned()),
+            multi_dist: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!("stable", json["Suite"]);
+        assert_eq!(true, json["MultiDist"]);
+        assert!(json.get("Codename").is_none());
+        assert!(serde_json::to_value(PublishOptions::default())
+            .unwrap()
+            .get("MultiDist")
+            .is_none());
+
+        let json = serde_json::to_value(UpdateOptions {
+            but_automatic_upgrades: Some(true),
+            multi_dist: Some(false),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!("yes", json["ButAutomaticUpgrades"]);
+        assert_eq!(false, json["MultiDist"]);
+        let json = serde_json::to_value(UpdateOptions::default()).unwrap();
+        assert!(json.get("ButAutomaticUpgrades").is_none());
+        assert!(json.get("MultiDist").is_none());
+    }
+
+    #[test]
+    fn switch_fills_single_component() {
+        let p = published("snapshot", &["main"]);
+        let resolved =
+            switch_sources(&p, &["main-new".to_owned()], &[source("main-new", None)]).unwrap();
+
+        assert_eq!(1, resolved.len());
+        assert_eq!(Some("main"), resolved[0].component.as_deref());
+    }
+
+    #[test]
+    fn switch_validation() {
+        let p = published("snapshot", &["main", "contrib"]);
+        let existing = ["new".to_owned()];
+
+        assert!(matches!(
+            switch_sources(&p, &existing, &[source("new", None)]),
+            Err(SwitchError::MissingComponent(_))
+        ));
+        assert!(matches!(
+            switch_sources(&p, &existing, &[source("new", Some("non-free"))]),
+            Err(SwitchError::UnknownComponent(_))
+        ));
+        assert!(matches!(
+            switch_sources(&p, &existing, &[source("missing", Some("main"))]),
+            Err(SwitchError::UnknownSnapshot(_))
+        ));
+        assert!(matches!(
+            switch_sources(
+                &p,
+                &existing,
+                &[source("new", Some("main")), source("new", Some("main"))]
+            ),
+            Err(SwitchError::DuplicateComponent(_))
+        ));
+        assert!(matches!(
+            switch_sources(&published("local", &["main"]), &existing, &[]),
+            Err(SwitchError::NotSnapshotPublish(_))
+        ));
+    }
+}
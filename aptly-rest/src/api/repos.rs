@@ -1,8 +1,11 @@
+use std::collections::HashSet;
+
+use futures::stream::BoxStream;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr, NoneAsEmptyString};
 
-use crate::{key::AptlyKey, AptlyRestError};
+use crate::{api::tasks::TaskApi, key::AptlyKey, query::Query, AptlyRestError};
 
 #[derive(Debug, Clone)]
 pub struct RepoApi<'a> {
@@ -25,6 +28,13 @@ impl RepoApi<'_> {
             .await
     }
 
+    /// Change the settings of the repository, returning the updated repo
+    pub async fn edit(&self, options: &EditOptions) -> Result<Repo, AptlyRestError> {
+        self.aptly
+            .put_body(self.aptly.url(&["api", "repos", &self.name]), options)
+            .await
+    }
+
     pub async fn snapshot(
         &self,
         name: &str,
@@ -46,6 +56,40 @@ impl RepoApi<'_> {
             .await
     }
 
+    /// Add the selected packages of this repository to the `target`
+    /// repository, returning the keys of the copied packages. Nothing is
+    /// copied if any explicitly selected package isn't in this repository.
+    pub async fn copy_packages(
+        &self,
+        target: &str,
+        selection: &PackageSelection,
+    ) -> Result<Vec<AptlyKey>, AptlyRestError> {
+        let keys = self.packages().select(selection).await?;
+        if !keys.is_empty() {
+            self.aptly.repo(target).packages().add(&keys).await?;
+        }
+        Ok(keys)
+    }
+
+    /// Like [RepoApi::copy_packages], but also remove the packages from this
+    /// repository once they've been added to `target`.
+    ///
+    /// This is not atomic: the packages are first added to `target` and then
+    /// deleted from this repository in a separate request. If the deletion
+    /// fails, the packages are left in both repositories and the move can
+    /// simply be retried.
+    pub async fn move_packages(
+        &self,
+        target: &str,
+        selection: &PackageSelection,
+    ) -> Result<Vec<AptlyKey>, AptlyRestError> {
+        let keys = self.copy_packages(target, selection).await?;
+        if !keys.is_empty() {
+            self.packages().delete(&keys).await?;
+        }
+        Ok(keys)
+    }
+
     pub async fn delete(&self, options: &DeleteOptions) -> Result<(), AptlyRestError> {
         let mut url = self.aptly.url(&["api", "repos", &self.name]);
 
@@ -112,6 +156,24 @@ impl RepoApiPackages<'_> {
         self.repo.aptly.get(url).await
     }
 
+    fn do_list_stream(
+        &self,
+        query: Option<&str>,
+        with_deps: bool,
+    ) -> BoxStream<'static, Result<AptlyKey, AptlyRestError>> {
+        let url = self.search_url(query, with_deps, false);
+        self.repo.aptly.get_stream(url)
+    }
+
+    fn do_detailed_stream(
+        &self,
+        query: Option<&str>,
+        with_deps: bool,
+    ) -> BoxStream<'static, Result<Package, AptlyRestError>> {
+        let url = self.search_url(query, with_deps, true);
+        self.repo.aptly.get_stream(url)
+    }
+
     pub async fn list(&self) -> Result<Vec<AptlyKey>, AptlyRestError> {
         self.do_list(None, false).await
     }
@@ -120,14 +182,62 @@ impl RepoApiPackages<'_> {
         self.do_detailed(None, false).await
     }
 
-    pub fn query(&self, query: String, with_deps: bool) -> RepoApiPackagesQuery<'_> {
+    /// Like [Self::list], but yields the keys while the response is still
+    /// being received
+    pub fn list_stream(&self) -> BoxStream<'static, Result<AptlyKey, AptlyRestError>> {
+        self.do_list_stream(None, false)
+    }
+
+    /// Like [Self::detailed], but deserializes the packages one at a time
+    /// while the response is being received, rather than buffering all of
+    /// them in memory
+    pub fn detailed_stream(&self) -> BoxStream<'static, Result<Package, AptlyRestError>> {
+        self.do_detailed_stream(None, false)
+    }
+
+    /// Packages matching `query`, a [crate::query::Query] or a raw query
+    /// string
+    pub fn query(&self, query: impl ToString, with_deps: bool) -> RepoApiPackagesQuery<'_> {
         RepoApiPackagesQuery {
             parent: self,
-            query,
+            query: query.to_string(),
             with_deps,
         }
     }
 
+    /// Resolve a [PackageSelection] to the keys of the packages it selects.
+    ///
+    /// Explicit keys are checked against the repository, failing with
+    /// [AptlyRestError::PackagesNotInRepo] if any of them aren't in it.
+    pub async fn select(
+        &self,
+        selection: &PackageSelection,
+    ) -> Result<Vec<AptlyKey>, AptlyRestError> {
+        match selection {
+            PackageSelection::Query { query, with_deps } => {
+                self.do_list(Some(&query.to_string()), *with_deps).await
+            }
+            PackageSelection::Keys(keys) => {
+                if keys.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let existing: HashSet<AptlyKey> = self.list().await?.into_iter().collect();
+                let missing: Vec<_> = keys
+                    .iter()
+                    .filter(|k| !existing.contains(k))
+                    .cloned()
+                    .collect();
+                if !missing.is_empty() {
+                    return Err(AptlyRestError::PackagesNotInRepo {
+                        repo: self.repo.name.clone(),
+                        keys: missing,
+                    });
+                }
+                Ok(keys.clone())
+            }
+        }
+    }
+
     pub async fn add<'r, R>(&self, keys: R) -> Result<Repo, AptlyRestError>
     where
         R: IntoIterator<Item = &'r AptlyKey>,
@@ -190,6 +300,16 @@ impl RepoApiPackagesQuery<'_> {
             .do_detailed(Some(&self.query), self.with_deps)
             .await
     }
+
+    pub fn list_stream(&self) -> BoxStream<'static, Result<AptlyKey, AptlyRestError>> {
+        self.parent
+            .do_list_stream(Some(&self.query), self.with_deps)
+    }
+
+    pub fn detailed_stream(&self) -> BoxStream<'static, Result<Package, AptlyRestError>> {
+        self.parent
+            .do_detailed_stream(Some(&self.query), self.with_deps)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -197,7 +317,7 @@ pub struct RepoApiFiles<'a> {
     repo: &'a crate::RepoApi<'a>,
 }
 
-impl RepoApiFiles<'_> {
+impl<'a> RepoApiFiles<'a> {
     fn url(&self, directory: &str, filename: Option<&str>, options: &AddPackageOptions) -> Url {
         let mut path = vec!["api", "repos", &self.repo.name, "file", directory];
         if let Some(filename) = filename {
@@ -218,6 +338,37 @@ impl RepoApiFiles<'_> {
         url
     }
 
+    fn include_url(
+        &self,
+        directory: &str,
+        filename: Option<&str>,
+        options: &IncludeOptions,
+    ) -> Url {
+        let mut path = vec!["api", "repos", &self.repo.name, "include", directory];
+        if let Some(filename) = filename {
+            path.push(filename);
+        }
+
+        let mut url = self.repo.aptly.url(path);
+
+        let mut pairs = url.query_pairs_mut();
+        if options.force_replace {
+            pairs.append_pair("forceReplace", "1");
+        }
+        if options.no_remove_files {
+            pairs.append_pair("noRemoveFiles", "1");
+        }
+        if options.accept_unsigned {
+            pairs.append_pair("acceptUnsigned", "1");
+        }
+        if options.ignore_signature {
+            pairs.append_pair("ignoreSignature", "1");
+        }
+
+        drop(pairs);
+        url
+    }
+
     pub async fn add_directory(
         &self,
         directory: &str,
@@ -229,6 +380,18 @@ impl RepoApiFiles<'_> {
             .await
     }
 
+    /// Like [RepoApiFiles::add_directory], but run as an aptly background
+    /// task; the task's result is an [AddPackageResponse].
+    pub async fn add_directory_async(
+        &self,
+        directory: &str,
+        options: &AddPackageOptions,
+    ) -> Result<TaskApi<'a>, AptlyRestError> {
+        let aptly: &'a crate::AptlyRest = self.repo.aptly;
+        let url = crate::AptlyRest::async_url(self.url(directory, None, options));
+        aptly.task_request(aptly.client.post(url)).await
+    }
+
     pub async fn add_file(
         &self,
         directory: &str,
@@ -240,6 +403,32 @@ impl RepoApiFiles<'_> {
             .post(self.url(directory, Some(filename), options))
             .await
     }
+
+    /// Include all `.changes` files in an upload directory, adding the
+    /// packages they reference to the repository.
+    pub async fn include(
+        &self,
+        directory: &str,
+        options: &IncludeOptions,
+    ) -> Result<AddPackageResponse, AptlyRestError> {
+        self.repo
+            .aptly
+            .post(self.include_url(directory, None, options))
+            .await
+    }
+
+    /// Include a single `.changes` file from an upload directory.
+    pub async fn include_file(
+        &self,
+        directory: &str,
+        filename: &str,
+        options: &IncludeOptions,
+    ) -> Result<AddPackageResponse, AptlyRestError> {
+        self.repo
+            .aptly
+            .post(self.include_url(directory, Some(filename), options))
+            .await
+    }
 }
 
 #[serde_as]
@@ -305,6 +494,27 @@ pub struct AddPackageOptions {
     pub force_replace: bool,
 }
 
+/// Packages of a repository to operate on
+#[derive(Debug, Clone)]
+pub enum PackageSelection {
+    /// Packages matching an aptly query, optionally along with their
+    /// dependencies from the same repository
+    Query { query: Query, with_deps: bool },
+    /// Exactly these packages
+    Keys(Vec<AptlyKey>),
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct IncludeOptions {
+    pub force_replace: bool,
+    /// Keep the uploaded files after they have been included
+    pub no_remove_files: bool,
+    /// Allow `.changes` files without a signature
+    pub accept_unsigned: bool,
+    /// Don't verify the signature of `.changes` files
+    pub ignore_signature: bool,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 #[serde(rename_all = "PascalCase")]
 pub struct AddPackageResponse {
@@ -344,6 +554,24 @@ impl OperationReport {
     }
 }
 
+/// Repository settings to change; fields left as `None` keep their current
+/// value, while an empty string clears them.
+#[derive(Default, Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EditOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(
+        rename = "DefaultDistribution",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub distribution: Option<String>,
+    #[serde(rename = "DefaultComponent", skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+}
+
 #[derive(Default, Debug, Clone, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct SnapshotOptions {
@@ -482,6 +710,24 @@ mod test {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn serialize_edit_options() {
+        let v = serde_json::to_value(EditOptions {
+            distribution: Some("bookworm".to_owned()),
+            comment: Some(String::new()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            json!({
+                "Comment": "",
+                "DefaultDistribution": "bookworm"
+            }),
+            v
+        );
+    }
+
     #[test]
     fn deserialize_binary() {
         let v: Package = serde_json::from_value(json!({
@@ -1,7 +1,9 @@
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 
-use crate::AptlyRestError;
+use crate::{
+    api::repos::Package, key::AptlyKey, query::Query, version::Capability, AptlyRestError,
+};
 
 #[derive(Debug, Clone)]
 pub struct SnapshotApi<'a> {
@@ -14,10 +16,114 @@ impl SnapshotApi<'_> {
         self.aptly.url(&["api", "snapshots", &self.name])
     }
 
+    pub fn packages(&self) -> SnapshotApiPackages<'_> {
+        SnapshotApiPackages { snapshot: self }
+    }
+
     pub async fn get(&self) -> Result<Snapshot, AptlyRestError> {
         self.aptly.get(self.url()).await
     }
 
+    /// Rename the snapshot or change its description, returning the updated
+    /// snapshot
+    pub async fn edit(&self, options: &EditOptions) -> Result<Snapshot, AptlyRestError> {
+        self.aptly.put_body(self.url(), options).await
+    }
+
+    /// Compare this snapshot against another one; packages only in this
+    /// snapshot are on the left, packages only in `other` on the right.
+    pub async fn diff(&self, other: &str) -> Result<Vec<SnapshotDiff>, AptlyRestError> {
+        self.aptly
+            .get(
+                self.aptly
+                    .url(&["api", "snapshots", &self.name, "diff", other]),
+            )
+            .await
+    }
+
+    /// Create the snapshot `destination` from this snapshot with packages
+    /// matching `queries` pulled in from the `source` snapshot, replacing
+    /// older versions of those packages.
+    pub async fn pull(
+        &self,
+        source: &str,
+        destination: &str,
+        queries: &[Query],
+        options: &PullOptions,
+    ) -> Result<Snapshot, AptlyRestError> {
+        #[derive(Debug, Clone, Serialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct PullRequest<'a> {
+            source: &'a str,
+            destination: &'a str,
+            queries: &'a [Query],
+            #[serde(skip_serializing_if = "<[_]>::is_empty")]
+            architectures: &'a [String],
+        }
+
+        self.aptly.require(Capability::SnapshotPull).await?;
+
+        let mut url = self.aptly.url(&["api", "snapshots", &self.name, "pull"]);
+        {
+            let mut pairs = url.query_pairs_mut();
+            if options.no_deps {
+                pairs.append_pair("no-deps", "1");
+            }
+            if options.no_remove {
+                pairs.append_pair("no-remove", "1");
+            }
+            if options.all_matches {
+                pairs.append_pair("all-matches", "1");
+            }
+        }
+
+        self.aptly
+            .post_body(
+                url,
+                &PullRequest {
+                    source,
+                    destination,
+                    queries,
+                    architectures: &options.architectures,
+                },
+            )
+            .await
+    }
+
+    /// Create the snapshot `destination` containing only the packages of
+    /// this snapshot that match any of `queries`, which must not be empty.
+    pub async fn filter(
+        &self,
+        destination: &str,
+        queries: &[Query],
+        options: &FilterOptions,
+    ) -> Result<Snapshot, AptlyRestError> {
+        // An empty query would match every package rather than none
+        if queries.is_empty() {
+            return Err(AptlyRestError::EmptyFilter {
+                snapshot: self.name.clone(),
+            });
+        }
+
+        let query = Query::Or(queries.to_vec());
+        let keys = self
+            .packages()
+            .query(query, options.with_deps)
+            .list()
+            .await?;
+
+        self.aptly
+            .create_snapshot(
+                destination,
+                &keys,
+                &CreateOptions {
+                    description: options.description.clone(),
+                    source_snapshots: vec![self.name.clone()],
+                },
+            )
+            .await
+    }
+
     pub async fn delete(&self, options: &DeleteOptions) -> Result<(), AptlyRestError> {
         let mut url = self.url();
 
@@ -35,6 +141,90 @@ impl SnapshotApi<'_> {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct SnapshotApiPackages<'a> {
+    snapshot: &'a SnapshotApi<'a>,
+}
+
+impl SnapshotApiPackages<'_> {
+    fn url(&self, query: Option<&str>, with_deps: bool, detailed: bool) -> Url {
+        let mut url =
+            self.snapshot
+                .aptly
+                .url(&["api", "snapshots", &self.snapshot.name, "packages"]);
+
+        let mut pairs = url.query_pairs_mut();
+        if let Some(query) = query {
+            pairs.append_pair("q", query);
+            if with_deps {
+                pairs.append_pair("withDeps", "1");
+            }
+        }
+
+        if detailed {
+            pairs.append_pair("format", "details");
+        }
+
+        drop(pairs);
+        url
+    }
+
+    async fn do_list(
+        &self,
+        query: Option<&str>,
+        with_deps: bool,
+    ) -> Result<Vec<AptlyKey>, AptlyRestError> {
+        let url = self.url(query, with_deps, false);
+        self.snapshot.aptly.get(url).await
+    }
+
+    async fn do_detailed(
+        &self,
+        query: Option<&str>,
+        with_deps: bool,
+    ) -> Result<Vec<Package>, AptlyRestError> {
+        let url = self.url(query, with_deps, true);
+        self.snapshot.aptly.get(url).await
+    }
+
+    pub async fn list(&self) -> Result<Vec<AptlyKey>, AptlyRestError> {
+        self.do_list(None, false).await
+    }
+
+    pub async fn detailed(&self) -> Result<Vec<Package>, AptlyRestError> {
+        self.do_detailed(None, false).await
+    }
+
+    /// Packages matching `query`, a [crate::query::Query] or a raw query
+    /// string
+    pub fn query(&self, query: impl ToString, with_deps: bool) -> SnapshotApiPackagesQuery<'_> {
+        SnapshotApiPackagesQuery {
+            parent: self,
+            query: query.to_string(),
+            with_deps,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SnapshotApiPackagesQuery<'a> {
+    parent: &'a SnapshotApiPackages<'a>,
+    query: String,
+    with_deps: bool,
+}
+
+impl SnapshotApiPackagesQuery<'_> {
+    pub async fn list(&self) -> Result<Vec<AptlyKey>, AptlyRestError> {
+        self.parent.do_list(Some(&self.query), self.with_deps).await
+    }
+
+    pub async fn detailed(&self) -> Result<Vec<Package>, AptlyRestError> {
+        self.parent
+            .do_detailed(Some(&self.query), self.with_deps)
+            .await
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct Snapshot {
@@ -59,7 +249,102 @@ impl Snapshot {
     }
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SnapshotDiff {
+    left: Option<AptlyKey>,
+    right: Option<AptlyKey>,
+}
+
+impl SnapshotDiff {
+    /// The package in the snapshot being compared
+    pub fn left(&self) -> Option<&AptlyKey> {
+        self.left.as_ref()
+    }
+
+    /// The package in the snapshot being compared against
+    pub fn right(&self) -> Option<&AptlyKey> {
+        self.right.as_ref()
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Snapshots the new snapshot is recorded as being derived from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub source_snapshots: Vec<String>,
+}
+
+/// Snapshot settings to change; fields left as `None` keep their current value.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EditOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// How packages that are in more than one source snapshot are merged; by
+/// default the version from the last source wins.
+#[derive(Debug, Default, Clone)]
+pub struct MergeOptions {
+    /// Keep only the latest version of each package
+    pub latest: bool,
+    /// Keep all versions of each package; can't be combined with `latest`
+    pub no_remove: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PullOptions {
+    /// Limit the pull to these architectures; defaults to those of the
+    /// snapshot being pulled into
+    pub architectures: Vec<String>,
+    /// Don't pull the dependencies of the matching packages
+    pub no_deps: bool,
+    /// Keep the existing versions of pulled packages
+    pub no_remove: bool,
+    /// Pull all packages matching a query rather than only the latest one
+    pub all_matches: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FilterOptions {
+    /// Also keep the dependencies of the matching packages
+    pub with_deps: bool,
+    pub description: Option<String>,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct DeleteOptions {
     pub force: bool,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deserialize_diff() {
+        let diff: Vec<SnapshotDiff> = serde_json::from_value(json!([
+            {
+                "Left": null,
+                "Right": "Pamd64 aptly 1.5.0+ds1-1 decafbaddecafbad"
+            },
+            {
+                "Left": "Pall systemd-doc 252.30-1 87415bdc9ef60793",
+                "Right": "Pall systemd-doc 252.31-1 1874ac1ecae98276"
+            }
+        ]))
+        .unwrap();
+
+        assert_eq!(2, diff.len());
+        assert!(diff[0].left().is_none());
+        assert_eq!("aptly", diff[0].right().unwrap().package());
+        assert!(diff[1].left().unwrap().version() < diff[1].right().unwrap().version());
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Disk usage as reported by `/api/storage`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Storage {
+    total: u64,
+    free: u64,
+    percent_full: f32,
+}
+
+impl Storage {
+    /// Size of the filesystem in MiB
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Space available to aptly in MiB
+    pub fn free(&self) -> u64 {
+        self.free
+    }
+
+    pub fn percent_full(&self) -> f32 {
+        self.percent_full
+    }
+}
@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::AptlyRestError;
+
+/// How often [TaskApi::wait] checks on a task by default
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct TaskApi<'a> {
+    pub(crate) aptly: &'a crate::AptlyRest,
+    pub(crate) id: u64,
+}
+
+impl TaskApi<'_> {
+    fn url(&self, action: Option<&str>) -> Url {
+        let id = self.id.to_string();
+        let mut path = vec!["api", "tasks", &id];
+        if let Some(action) = action {
+            path.push(action);
+        }
+
+        self.aptly.url(path)
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub async fn get(&self) -> Result<Task, AptlyRestError> {
+        self.aptly.get(self.url(None)).await
+    }
+
+    /// Poll the task until it has finished running, however long that takes
+    pub async fn wait(&self) -> Result<Task, AptlyRestError> {
+        self.wait_with_interval(DEFAULT_POLL_INTERVAL, None).await
+    }
+
+    /// Like [TaskApi::wait], but fail with [AptlyRestError::TaskTimeout] if
+    /// the task is still running after `timeout`
+    pub async fn wait_timeout(&self, timeout: Duration) -> Result<Task, AptlyRestError> {
+        self.wait_with_interval(DEFAULT_POLL_INTERVAL, Some(timeout))
+            .await
+    }
+
+    /// Poll the task every `interval` until it has finished running, giving
+    /// up after `timeout` if one is given
+    pub async fn wait_with_interval(
+        &self,
+        interval: Duration,
+        timeout: Option<Duration>,
+    ) -> Result<Task, AptlyRestError> {
+        let poll = async {
+            loop {
+                let task = self.get().await?;
+                if task.state().is_finished() {
+                    return Ok(task);
+                }
+
+                tokio::time::sleep(interval).await;
+            }
+        };
+
+        match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, poll).await.map_err(|_| {
+                AptlyRestError::TaskTimeout {
+                    id: self.id,
+                    timeout,
+                }
+            })?,
+            None => poll.await,
+        }
+    }
+
+    pub async fn output(&self) -> Result<String, AptlyRestError> {
+        self.aptly.get(self.url(Some("output"))).await
+    }
+
+    pub async fn detail(&self) -> Result<serde_json::Value, AptlyRestError> {
+        self.aptly.get(self.url(Some("detail"))).await
+    }
+
+    pub async fn return_value<T>(&self) -> Result<T, AptlyRestError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.aptly.get(self.url(Some("return_value"))).await
+    }
+
+    /// Wait for the task to finish and retrieve its return value, which is
+    /// what the synchronous version of the request would have returned.
+    pub async fn result<T>(&self) -> Result<T, AptlyRestError>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let task = self.wait().await?;
+        if task.state() == TaskState::Failed {
+            return Err(AptlyRestError::TaskFailed {
+                id: task.id(),
+                name: task.name().to_owned(),
+                output: self.output().await?,
+            });
+        }
+
+        self.return_value().await
+    }
+
+    pub async fn delete(&self) -> Result<(), AptlyRestError> {
+        self.aptly
+            .send_request(self.aptly.client.delete(self.url(None)))
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum TaskState {
+    Idle,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl TaskState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, TaskState::Succeeded | TaskState::Failed)
+    }
+}
+
+impl TryFrom<u8> for TaskState {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TaskState::Idle),
+            1 => Ok(TaskState::Running),
+            2 => Ok(TaskState::Succeeded),
+            3 => Ok(TaskState::Failed),
+            _ => Err(format!("invalid task state {value}")),
+        }
+    }
+}
+
+impl From<TaskState> for u8 {
+    fn from(state: TaskState) -> Self {
+        match state {
+            TaskState::Idle => 0,
+            TaskState::Running => 1,
+            TaskState::Succeeded => 2,
+            TaskState::Failed => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Task {
+    #[serde(rename = "ID")]
+    id: u64,
+    name: String,
+    state: TaskState,
+}
+
+impl Task {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn state(&self) -> TaskState {
+        self.state
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deserialize_task() {
+        let t: Task = serde_json::from_value(json!({
+            "Name": "Update published local repository //bookworm",
+            "ID": 4,
+            "State": 3
+        }))
+        .unwrap();
+
+        assert_eq!(4, t.id());
+        assert_eq!(TaskState::Failed, t.state());
+        assert!(t.state().is_finished());
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::Args;
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Certificate, Identity, Proxy,
+};
+use url::Url;
+
+use crate::{
+    builder::AptlyRestBuilder,
+    config::{Config, ConfigError, Profile},
+    AptlyRest, AptlyRestError,
+};
+
+const DEFAULT_API_URL: &str = "http://localhost:8080";
+
+#[derive(thiserror::Error, Debug)]
+pub enum AptlyArgsError {
+    #[error("Failed to read '{0}': {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error("Invalid header '{0}', expected 'Name: value'")]
+    InvalidHeader(String),
+    #[error("Invalid TLS or proxy setting: {0}")]
+    Setting(#[from] reqwest::Error),
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error(transparent)]
+    Client(#[from] AptlyRestError),
+}
+
+// Command line options for connecting to aptly, shared by all the tools; not a
+// doc comment, as clap would use it as the about text of every tool. Options
+// that aren't given are taken from the selected profile of the config file.
+#[derive(Args, Debug, Clone)]
+pub struct AptlyArgs {
+    /// Profile from the config file to connect with [default: the
+    /// default-profile of the config file]
+    #[clap(long, env = "APTLY_PROFILE")]
+    pub profile: Option<String>,
+    /// Config file with connection profiles [default:
+    /// ~/.config/aptly-rest-tools/config.toml]
+    #[clap(long, env = "APTLY_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Url for the aptly rest API endpoint, or unix:///path/to/socket
+    /// [default: http://localhost:8080]
+    #[clap(short = 'u', long, env = "APTLY_API_URL")]
+    pub api_url: Option<Url>,
+    /// Authentication token for the API
+    #[clap(long, env = "APTLY_API_TOKEN")]
+    pub api_token: Option<String>,
+    /// User for HTTP basic authentication
+    #[clap(long, env = "APTLY_API_USER", conflicts_with = "api_token")]
+    pub api_user: Option<String>,
+    /// Password for HTTP basic authentication
+    #[clap(long, env = "APTLY_API_PASSWORD", requires = "api_user")]
+    pub api_password: Option<String>,
+    /// PEM client certificate to authenticate with
+    #[clap(long, env = "APTLY_CLIENT_CERT", requires = "client_key")]
+    pub client_cert: Option<PathBuf>,
+    /// PEM (PKCS#8) private key of the client certificate
+    #[clap(long, env = "APTLY_CLIENT_KEY", requires = "client_cert")]
+    pub client_key: Option<PathBuf>,
+    /// Extra PEM CA certificate(s) to trust
+    #[clap(long = "ca-cert", env = "APTLY_CA_CERT", value_delimiter = ',')]
+    pub ca_certs: Vec<PathBuf>,
+    /// Extra header to send with every request, as 'Name: value'
+    #[clap(long = "header")]
+    pub headers: Vec<String>,
+    /// Proxy to use for all requests
+    #[clap(long, env = "APTLY_PROXY", conflicts_with = "no_proxy")]
+    pub proxy: Option<Url>,
+    /// Don't use proxies configured in the environment
+    #[clap(long)]
+    pub no_proxy: bool,
+    /// Seconds to wait for a connection to aptly
+    #[clap(long, env = "APTLY_CONNECT_TIMEOUT")]
+    pub connect_timeout: Option<u64>,
+    /// Seconds to wait for a request to finish
+    #[clap(long, env = "APTLY_TIMEOUT")]
+    pub timeout: Option<u64>,
+}
+
+impl AptlyArgs {
+    /// Load the config file and select the requested profile, if any. A
+    /// missing config file is only an error if a profile was asked for, and
+    /// an unreadable default config file is only an error if a profile or
+    /// the config file was asked for; otherwise it's ignored with a warning.
+    pub fn load_profile(&self) -> Result<Option<Profile>, AptlyArgsError> {
+        self.load_profile_from(Config::default_path())
+    }
+
+    fn load_profile_from(
+        &self,
+        default_path: Option<PathBuf>,
+    ) -> Result<Option<Profile>, AptlyArgsError> {
+        let Some(path) = self.config.clone().or(default_path) else {
+            return match &self.profile {
+                Some(name) => Err(ConfigError::UnknownProfile(name.clone()).into()),
+                None => Ok(None),
+            };
+        };
+        let config = match Config::load(&path) {
+            Ok(config) => config,
+            Err(e) if self.profile.is_none() && self.config.is_none() => {
+                tracing::warn!("Ignoring config file: {e}");
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(config.select(self.profile.as_deref())?.cloned())
+    }
+
+    /// These options with anything not given filled in from `profile`
+    pub fn merge(&self, profile: Profile) -> Self {
+        let mut merged = self.clone();
+        merged.api_url = merged.api_url.or(profile.url);
+        if merged.api_token.is_none() && merged.api_user.is_none() {
+            merged.api_token = profile.token;
+            merged.api_user = profile.user;
+            merged.api_password = profile.password;
+        }
+        if merged.client_cert.is_none() {
+            merged.client_cert = profile.client_cert;
+            merged.client_key = profile.client_key;
+        }
+        merged.ca_certs.extend(profile.ca_certs);
+        merged.headers = profile.headers.into_iter().chain(merged.headers).collect();
+        if merged.proxy.is_none() && !merged.no_proxy {
+            merged.proxy = profile.proxy;
+            merged.no_proxy = profile.no_proxy;
+        }
+        merged.connect_timeout = merged.connect_timeout.or(profile.connect_timeout);
+        merged.timeout = merged.timeout.or(profile.timeout);
+        merged
+    }
+
+    pub fn builder(&self) -> Result<AptlyRestBuilder, AptlyArgsError> {
+        match self.load_profile()? {
+            Some(profile) => self.merge(profile).builder_from_args(),
+            None => self.builder_from_args(),
+        }
+    }
+
+    fn builder_from_args(&self) -> Result<AptlyRestBuilder, AptlyArgsError> {
+        let url = self
+            .api_url
+            .clone()
+            .unwrap_or_else(|| Url::parse(DEFAULT_API_URL).unwrap());
+        let mut builder = AptlyRest::builder(url);
+
+        if let Some(token) = &self.api_token {
+            builder = builder.token(token.clone());
+        }
+        if let Some(user) = &self.api_user {
+            builder = builder.basic_auth(user.clone(), self.api_password.clone());
+        }
+
+        if let (Some(cert), Some(key)) = (&self.client_cert, &self.client_key) {
+            builder = builder.identity(Identity::from_pkcs8_pem(&read(cert)?, &read(key)?)?);
+        }
+        for ca in &self.ca_certs {
+            for certificate in Certificate::from_pem_bundle(&read(ca)?)? {
+                builder = builder.root_certificate(certificate);
+            }
+        }
+
+        for header in &self.headers {
+            let invalid = || AptlyArgsError::InvalidHeader(header.clone());
+            let (name, value) = header.split_once(':').ok_or_else(invalid)?;
+            let name: HeaderName = name.trim().parse().map_err(|_| invalid())?;
+            let value: HeaderValue = value.trim().parse().map_err(|_| invalid())?;
+            builder = builder.header(name, value);
+        }
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+        if self.no_proxy {
+            builder = builder.no_proxy();
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(Duration::from_secs(timeout));
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+
+        Ok(builder)
+    }
+
+    pub fn build(&self) -> Result<AptlyRest, AptlyArgsError> {
+        Ok(self.builder()?.build()?)
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, AptlyArgsError> {
+    std::fs::read(path).map_err(|e| AptlyArgsError::Read(path.to_owned(), e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Opts {
+        #[clap(flatten)]
+        aptly: AptlyArgs,
+    }
+
+    #[test]
+    fn merge_profile() {
+        let opts = Opts::parse_from(["test", "--api-token", "cli", "--timeout", "5"]);
+        let profile = Profile {
+            url: Some(Url::parse("https://aptly.example.com").unwrap()),
+            user: Some("ci".to_string()),
+            headers: vec!["X-Profile: 1".to_string()],
+            timeout: Some(600),
+            connect_timeout: Some(10),
+            ..Default::default()
+        };
+
+        let merged = opts.aptly.merge(profile);
+        assert_eq!(
+            Some("https://aptly.example.com/"),
+            merged.api_url.as_ref().map(Url::as_str)
+        );
+        assert_eq!(Some("cli"), merged.api_token.as_deref());
+        assert_eq!(None, merged.api_user);
+        assert_eq!(vec!["X-Profile: 1".to_string()], merged.headers);
+        assert_eq!(Some(5), merged.timeout);
+        assert_eq!(Some(10), merged.connect_timeout);
+    }
+
+    #[test]
+    fn invalid_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[profiles.local]\ntimeout = \"long\"\n").unwrap();
+
+        // Only a requested config file or profile has to be valid
+        let opts = Opts::parse_from(["test", "--config", path.to_str().unwrap()]);
+        assert!(matches!(
+            opts.aptly.load_profile_from(None),
+            Err(AptlyArgsError::Config(ConfigError::Parse { .. }))
+        ));
+
+        let opts = Opts::parse_from(["test", "--api-url", "http://aptly:8080"]);
+        assert_eq!(
+            None,
+            opts.aptly.load_profile_from(Some(path.clone())).unwrap()
+        );
+        let opts = Opts::parse_from(["test", "--profile", "local"]);
+        assert!(opts.aptly.load_profile_from(Some(path)).is_err());
+    }
+}
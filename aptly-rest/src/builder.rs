@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Certificate, Identity, Proxy,
+};
+use url::Url;
+
+use crate::{retry::RetryPolicy, AptlyRest, AptlyRestError};
+
+#[derive(Clone)]
+enum Auth {
+    None,
+    Token(String),
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+}
+
+// Keep secrets out of logs of the builder
+impl std::fmt::Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Auth::None => f.write_str("None"),
+            Auth::Token(_) => f.debug_tuple("Token").field(&"<redacted>").finish(),
+            Auth::Basic { username, password } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &password.as_ref().map(|_| "<redacted>"))
+                .finish(),
+        }
+    }
+}
+
+/// Configures the HTTP client of an [AptlyRest]; see [AptlyRest::builder].
+#[derive(Debug)]
+pub struct AptlyRestBuilder {
+    url: Url,
+    auth: Auth,
+    headers: HeaderMap,
+    identity: Option<Identity>,
+    root_certificates: Vec<Certificate>,
+    proxies: Vec<Proxy>,
+    no_proxy: bool,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    retry: RetryPolicy,
+}
+
+impl AptlyRestBuilder {
+    pub(crate) fn new(url: Url) -> Self {
+        Self {
+            url,
+            auth: Auth::None,
+            headers: HeaderMap::new(),
+            identity: None,
+            root_certificates: vec![],
+            proxies: vec![],
+            no_proxy: false,
+            connect_timeout: None,
+            timeout: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Authenticate with a Bearer token
+    pub fn token(self, token: String) -> Self {
+        Self {
+            auth: Auth::Token(token),
+            ..self
+        }
+    }
+
+    /// Authenticate with HTTP basic auth
+    pub fn basic_auth(self, username: String, password: Option<String>) -> Self {
+        Self {
+            auth: Auth::Basic { username, password },
+            ..self
+        }
+    }
+
+    /// Send this header with every request
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Client certificate to present to the server
+    pub fn identity(self, identity: Identity) -> Self {
+        Self {
+            identity: Some(identity),
+            ..self
+        }
+    }
+
+    /// Trust this certificate authority in addition to the system ones
+    pub fn root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Ignore proxies configured in the environment
+    pub fn no_proxy(self) -> Self {
+        Self {
+            no_proxy: true,
+            ..self
+        }
+    }
+
+    /// Limit how long connecting to the server may take
+    pub fn connect_timeout(self, timeout: Duration) -> Self {
+        Self {
+            connect_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Limit how long a whole request may take, including reading the
+    /// response body
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    pub fn retry_policy(self, retry: RetryPolicy) -> Self {
+        Self { retry, ..self }
+    }
+
+    pub fn build(self) -> Result<AptlyRest, AptlyRestError> {
+        let mut headers = self.headers;
+        match self.auth {
+            Auth::None => (),
+            Auth::Token(token) => {
+                let mut value: HeaderValue = format!("Bearer {token}").parse()?;
+                value.set_sensitive(true);
+                headers.insert(header::AUTHORIZATION, value);
+            }
+            Auth::Basic { username, password } => {
+                let mut value: HeaderValue =
+                    basic_auth_value(&username, password.as_deref()).parse()?;
+                value.set_sensitive(true);
+                headers.insert(header::AUTHORIZATION, value);
+            }
+        }
+
+        let mut client = reqwest::ClientBuilder::new().default_headers(headers);
+        if let Some(identity) = self.identity {
+            client = client.identity(identity);
+        }
+        for certificate in self.root_certificates {
+            client = client.add_root_certificate(certificate);
+        }
+        for proxy in self.proxies {
+            client = client.proxy(proxy);
+        }
+        if self.no_proxy {
+            client = client.no_proxy();
+        }
+        if let Some(timeout) = self.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+
+        let mut url = self.url;
+        #[cfg(unix)]
+        if let Some(socket) = unix_socket_path(&url) {
+            client = client.unix_socket(socket);
+            url = Url::parse("http://localhost/").unwrap();
+        }
+
+        Ok(AptlyRest {
+            client: client.build()?,
+            url,
+            retry: self.retry,
+            capabilities: Default::default(),
+        })
+    }
+}
+
+/// The socket path of a `unix:///path/to/socket` location, as used by
+/// aptly's `-listen` option. A host part is taken as the start of a relative
+/// path.
+#[cfg(unix)]
+fn unix_socket_path(url: &Url) -> Option<std::path::PathBuf> {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    if url.scheme() != "unix" {
+        return None;
+    }
+
+    let path = format!("{}{}", url.host_str().unwrap_or_default(), url.path());
+    let path: Vec<u8> = percent_encoding::percent_decode_str(&path).collect();
+    Some(OsStr::from_bytes(&path).into())
+}
+
+fn basic_auth_value(username: &str, password: Option<&str>) -> String {
+    use base64::Engine;
+
+    let credentials = format!("{username}:{}", password.unwrap_or_default());
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(credentials)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basic_auth() {
+        assert_eq!(
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==",
+            basic_auth_value("Aladdin", Some("open sesame"))
+        );
+        assert_eq!("Basic dXNlcjo=", basic_auth_value("user", None));
+    }
+
+    #[test]
+    fn redacted_debug() {
+        let url = Url::parse("http://localhost:8080").unwrap();
+        let builder = AptlyRestBuilder::new(url.clone()).token("s3cr3t".to_owned());
+        let debug = format!("{builder:?}");
+        assert!(!debug.contains("s3cr3t"), "{debug}");
+        assert!(debug.contains("Token(\"<redacted>\")"), "{debug}");
+
+        let builder =
+            AptlyRestBuilder::new(url).basic_auth("ci".to_owned(), Some("s3cr3t".to_owned()));
+        let debug = format!("{builder:?}");
+        assert!(!debug.contains("s3cr3t"), "{debug}");
+        assert!(debug.contains("username: \"ci\""), "{debug}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket() {
+        let url = Url::parse("unix:///run/aptly/api%20socket").unwrap();
+        assert_eq!(
+            Some(std::path::PathBuf::from("/run/aptly/api socket")),
+            unix_socket_path(&url)
+        );
+
+        let url = Url::parse("unix://aptly.sock").unwrap();
+        assert_eq!(
+            Some(std::path::PathBuf::from("aptly.sock")),
+            unix_socket_path(&url)
+        );
+
+        let url = Url::parse("http://localhost:8080").unwrap();
+        assert_eq!(None, unix_socket_path(&url));
+    }
+}
@@ -0,0 +1,247 @@
+//! Shared configuration file for the aptly tools.
+//!
+//! The file lives at `$XDG_CONFIG_HOME/aptly-rest-tools/config.toml`
+//! (`~/.config/aptly-rest-tools/config.toml` by default) and describes how
+//! to reach one or more aptly instances as named profiles:
+//!
+//! ```toml
+//! default-profile = "staging"
+//!
+//! [profiles.production]
+//! url = "https://aptly.example.com"
+//! token = "secret"
+//! ca-certs = ["internal-ca.pem"]
+//! timeout = 600
+//!
+//! [profiles.staging]
+//! url = "https://aptly-staging.example.com"
+//! user = "ci"
+//! password = "secret"
+//!
+//! [profiles.local]
+//! url = "unix:///run/aptly/api.sock"
+//! ```
+//!
+//! Relative paths are resolved against the directory of the config file.
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use serde_with::{serde_as, DisplayFromStr};
+use url::Url;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read '{0}': {1}")]
+    Read(PathBuf, std::io::Error),
+    #[error(
+        "Failed to parse '{path}'{}: {message}",
+        line.map(|l| format!(" at line {l}")).unwrap_or_default()
+    )]
+    Parse {
+        path: PathBuf,
+        line: Option<usize>,
+        message: String,
+    },
+    #[error("No profile '{0}' in the configuration")]
+    UnknownProfile(String),
+}
+
+/// Settings for reaching one aptly instance
+#[serde_as]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", default, deny_unknown_fields)]
+pub struct Profile {
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub url: Option<Url>,
+    pub token: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    pub ca_certs: Vec<PathBuf>,
+    pub headers: Vec<String>,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub proxy: Option<Url>,
+    pub no_proxy: bool,
+    /// In seconds
+    pub connect_timeout: Option<u64>,
+    /// In seconds
+    pub timeout: Option<u64>,
+}
+
+impl Profile {
+    fn resolve_paths(&mut self, base: &Path) {
+        for path in self
+            .client_cert
+            .iter_mut()
+            .chain(self.client_key.iter_mut())
+            .chain(self.ca_certs.iter_mut())
+        {
+            *path = base.join(&*path);
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    default_profile: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    /// Location of the config file, if a home or config directory is known
+    pub fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".config")))?;
+        Some(config_dir.join("aptly-rest-tools").join("config.toml"))
+    }
+
+    /// Load the config file at `path`; a missing file is an empty config.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Self::parse(&contents, path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(ConfigError::Read(path.to_owned(), e)),
+        }
+    }
+
+    /// Parse config file contents; `path` is used to resolve relative paths
+    /// and in errors.
+    pub fn parse(contents: &str, path: &Path) -> Result<Self, ConfigError> {
+        let mut config: Config = toml::from_str(contents).map_err(|e| ConfigError::Parse {
+            path: path.to_owned(),
+            line: e
+                .span()
+                .map(|span| contents[..span.start].matches('\n').count() + 1),
+            message: e.message().trim().to_owned(),
+        })?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for profile in config.profiles.values_mut() {
+            profile.resolve_paths(base);
+        }
+
+        Ok(config)
+    }
+
+    pub fn default_profile(&self) -> Option<&str> {
+        self.default_profile.as_deref()
+    }
+
+    pub fn profiles(&self) -> impl Iterator<Item = (&str, &Profile)> {
+        self.profiles.iter().map(|(name, p)| (name.as_str(), p))
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile, ConfigError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownProfile(name.to_owned()))
+    }
+
+    /// The profile with the given name, or else the default profile if the
+    /// config names one
+    pub fn select(&self, name: Option<&str>) -> Result<Option<&Profile>, ConfigError> {
+        name.or(self.default_profile())
+            .map(|name| self.profile(name))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = r#"
+default-profile = "staging"
+
+[profiles.production]
+url = "https://aptly.example.com"
+token = "secret"
+ca-certs = ["ca.pem", "/etc/ssl/other.pem"]
+timeout = 600
+
+[profiles.staging]
+url = "https://aptly-staging.example.com"
+user = "ci"
+"#;
+
+    #[test]
+    fn parse() {
+        let config = Config::parse(CONFIG, Path::new("/etc/aptly/config.toml")).unwrap();
+
+        assert_eq!(Some("staging"), config.default_profile());
+        let production = config.profile("production").unwrap();
+        assert_eq!(
+            "https://aptly.example.com/",
+            production.url.as_ref().unwrap().as_str()
+        );
+        assert_eq!(Some("secret"), production.token.as_deref());
+        assert_eq!(
+            vec![
+                PathBuf::from("/etc/aptly/ca.pem"),
+                PathBuf::from("/etc/ssl/other.pem")
+            ],
+            production.ca_certs
+        );
+        assert_eq!(Some(600), production.timeout);
+
+        let selected = config.select(None).unwrap().unwrap();
+        assert_eq!(Some("ci"), selected.user.as_deref());
+        assert!(matches!(
+            config.select(Some("missing")),
+            Err(ConfigError::UnknownProfile(_))
+        ));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let err = Config::parse(
+            "[profiles.local]\nurl = \"unix:///run/aptly.sock\"\ntimeout = \"long\"\n",
+            Path::new("config.toml"),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::Parse { line: Some(3), .. }),
+            "{err}"
+        );
+        assert!(err
+            .to_string()
+            .starts_with("Failed to parse 'config.toml' at line 3"));
+
+        let err = Config::parse("[profiles.local]\nurls = \"x\"\n", Path::new("config.toml"))
+            .unwrap_err();
+        assert!(
+            matches!(&err, ConfigError::Parse { line: Some(2), message, .. } if message.contains("urls")),
+            "{err}"
+        );
+
+        let err = Config::parse(
+            "[profiles.local]\nurl = \"not a url\"\n",
+            Path::new("c.toml"),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, ConfigError::Parse { line: Some(2), .. }),
+            "{err}"
+        );
+
+        let err = Config::parse("default-profile = 1\n", Path::new("config.toml")).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::Parse { line: Some(1), .. }),
+            "{err}"
+        );
+
+        let err = Config::parse("[profiles\n", Path::new("config.toml")).unwrap_err();
+        assert!(
+            matches!(err, ConfigError::Parse { line: Some(1), .. }),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use futures::{Stream, StreamExt};
+use serde::de::{DeserializeOwned, Error};
+
+use crate::AptlyRestError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Before the opening `[`
+    Start,
+    /// Expecting an element, or `]` if the array is still empty
+    Element {
+        first: bool,
+    },
+    /// Inside the element starting at the given offset of the buffer
+    InElement {
+        start: usize,
+    },
+    /// Expecting `,` or `]` after an element
+    Separator,
+    Done,
+}
+
+/// Splits a JSON array that arrives in chunks into the raw bytes of its
+/// elements, so only a single element has to be kept in memory at a time.
+#[derive(Debug)]
+struct ArraySplitter {
+    buf: Vec<u8>,
+    pos: usize,
+    state: State,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl ArraySplitter {
+    fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            pos: 0,
+            state: State::Start,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// The next complete element, if the buffer has one
+    fn next_element(&mut self) -> Result<Option<Vec<u8>>, serde_json::Error> {
+        while self.pos < self.buf.len() {
+            let c = self.buf[self.pos];
+            match self.state {
+                State::InElement { start } => {
+                    if let Some(end) = self.element_end(c) {
+                        let element = self.buf[start..end].to_vec();
+                        // Look at the byte after the element again; consumed
+                        // bytes are dropped once the buffer is exhausted
+                        self.pos = end;
+                        self.state = State::Separator;
+                        return Ok(Some(element));
+                    }
+                }
+                _ if c.is_ascii_whitespace() => (),
+                State::Start if c == b'[' => self.state = State::Element { first: true },
+                State::Element { first: true } if c == b']' => self.state = State::Done,
+                State::Element { .. } => {
+                    self.state = State::InElement { start: self.pos };
+                    // Look at this byte again as part of the element
+                    continue;
+                }
+                State::Separator if c == b',' => self.state = State::Element { first: false },
+                State::Separator if c == b']' => self.state = State::Done,
+                _ => {
+                    return Err(serde_json::Error::custom(format!(
+                        "unexpected '{}' in JSON array",
+                        c.escape_ascii()
+                    )))
+                }
+            }
+            self.pos += 1;
+        }
+
+        // Only keep the bytes of an incomplete element around
+        match self.state {
+            State::InElement { start } => {
+                self.buf.drain(..start);
+                self.pos -= start;
+                self.state = State::InElement { start: 0 };
+            }
+            _ => {
+                self.buf.clear();
+                self.pos = 0;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Feed the byte at `pos` of the current element, returning the end
+    /// offset of the element if the byte completes it.
+    fn element_end(&mut self, c: u8) -> Option<usize> {
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if c == b'\\' {
+                self.escaped = true;
+            } else if c == b'"' {
+                self.in_string = false;
+                if self.depth == 0 {
+                    return Some(self.pos + 1);
+                }
+            }
+            return None;
+        }
+
+        match c {
+            b'"' => self.in_string = true,
+            b'{' | b'[' => self.depth += 1,
+            b'}' | b']' if self.depth > 0 => {
+                self.depth -= 1;
+                if self.depth == 0 {
+                    return Some(self.pos + 1);
+                }
+            }
+            // The end of a number or literal
+            b',' | b']' if self.depth == 0 => return Some(self.pos),
+            c if c.is_ascii_whitespace() && self.depth == 0 => return Some(self.pos),
+            _ => (),
+        }
+        None
+    }
+
+    fn finish(&self) -> Result<(), serde_json::Error> {
+        if self.state == State::Done {
+            Ok(())
+        } else {
+            Err(serde_json::Error::custom("unexpected end of JSON array"))
+        }
+    }
+}
+
+/// Deserialize the elements of a JSON array from a stream of body chunks as
+/// they arrive
+pub(crate) fn array<T, S, B, E>(bytes: S) -> impl Stream<Item = Result<T, AptlyRestError>>
+where
+    T: DeserializeOwned,
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    AptlyRestError: From<E>,
+{
+    futures::stream::try_unfold(
+        (bytes, ArraySplitter::new()),
+        |(mut bytes, mut splitter)| async move {
+            loop {
+                if let Some(element) = splitter.next_element()? {
+                    let item = serde_json::from_slice(&element)?;
+                    return Ok(Some((item, (bytes, splitter))));
+                }
+                match bytes.next().await {
+                    Some(chunk) => splitter.push(chunk?.as_ref()),
+                    None => {
+                        splitter.finish()?;
+                        return Ok(None);
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use futures::TryStreamExt;
+    use serde_json::json;
+
+    use super::*;
+
+    async fn split(chunks: &[&str]) -> Result<Vec<serde_json::Value>, AptlyRestError> {
+        let bytes = futures::stream::iter(
+            chunks
+                .iter()
+                .map(|c| Ok::<_, AptlyRestError>(c.as_bytes().to_vec()))
+                .collect::<Vec<_>>(),
+        );
+        array(bytes).try_collect().await
+    }
+
+    #[tokio::test]
+    async fn split_chunks() {
+        let body = r#" [ {"Package": "a,]\"}", "Depends": ["x", {"y": []}]}, "P b", 12 ,true,
+            [1, [2]], null ] "#;
+
+        let expected = vec![
+            json!({"Package": "a,]\"}", "Depends": ["x", {"y": []}]}),
+            json!("P b"),
+            json!(12),
+            json!(true),
+            json!([1, [2]]),
+            json!(null),
+        ];
+        assert_eq!(expected, split(&[body]).await.unwrap());
+
+        // Every possible split into chunks gives the same result
+        for i in 0..body.len() {
+            let (a, b) = body.split_at(i);
+            assert_eq!(expected, split(&[a, b]).await.unwrap(), "split at {i}");
+        }
+
+        let bytes: Vec<String> = body.chars().map(String::from).collect();
+        let bytes: Vec<&str> = bytes.iter().map(String::as_str).collect();
+        assert_eq!(expected, split(&bytes).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn split_errors() {
+        assert!(split(&["[]"]).await.unwrap().is_empty());
+        assert!(split(&[" [ ] "]).await.unwrap().is_empty());
+        assert!(split(&["{}"]).await.is_err());
+        assert!(split(&["[1, 2"]).await.is_err());
+        assert!(split(&["[1 2]"]).await.is_err());
+        assert!(split(&["[1] 2"]).await.is_err());
+        assert!(split(&["[{\"a\": }]"]).await.is_err());
+    }
+}
@@ -1,20 +1,43 @@
+use std::sync::Arc;
+
 use api::{
     files::FilesApi,
+    gpg::GpgApi,
+    mirrors::{Mirror, MirrorApi},
     packages::PackagesApi,
     publish::{PublishApi, PublishedRepo},
     repos::{Repo, RepoApi},
     snapshots::{Snapshot, SnapshotApi},
+    storage::Storage,
+    tasks::{Task, TaskApi},
 };
-use reqwest::header;
+use builder::AptlyRestBuilder;
+use futures::{stream::BoxStream, StreamExt, TryFutureExt};
+use key::AptlyKey;
+use metrics::{Metrics, MetricsParseError};
+use prefix::PublishPrefix;
+use reqwest::{header, StatusCode};
+use retry::RetryPolicy;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::sync::OnceCell;
 use url::Url;
+use version::{AptlyVersion, Capabilities, Capability, VersionParseError};
 
 pub mod api;
+pub mod args;
+pub mod builder;
 pub mod changes;
+pub mod config;
 pub mod dsc;
+mod json_stream;
 pub mod key;
+pub mod metrics;
+pub mod prefix;
+pub mod query;
+pub mod retry;
 pub mod utils;
+pub mod version;
 
 #[derive(Error, Debug)]
 pub enum AptlyRestError {
@@ -22,47 +45,117 @@ pub enum AptlyRestError {
     Request(#[from] reqwest::Error),
     #[error("Invalid authentication token {0}")]
     InvalidAuthToken(#[from] header::InvalidHeaderValue),
+    #[error("Invalid response {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Not found: {message}")]
+    NotFound { message: String },
+    #[error("Conflict: {message}")]
+    Conflict { message: String },
+    #[error("Bad request: {message}")]
+    BadRequest { message: String },
+    #[error("Server error ({status}): {message}")]
+    Server { status: StatusCode, message: String },
+    /// Any other unsuccessful response, e.g. from an authenticating proxy
+    #[error("Request failed ({status}): {message}")]
+    Status { status: StatusCode, message: String },
+    #[error(transparent)]
+    Switch(#[from] api::publish::SwitchError),
+    #[error(transparent)]
+    Checksum(#[from] api::files::ChecksumError),
+    #[error(transparent)]
+    Metrics(#[from] MetricsParseError),
+    #[error(transparent)]
+    InvalidVersion(#[from] VersionParseError),
+    #[error("{capability} is unsupported by aptly {version}, it needs {} or later", capability.since())]
+    Unsupported {
+        capability: Capability,
+        version: AptlyVersion,
+    },
+    #[error("{} package(s) not in repository '{repo}'", keys.len())]
+    PackagesNotInRepo { repo: String, keys: Vec<AptlyKey> },
+    #[error("No queries given to filter snapshot '{snapshot}' with")]
+    EmptyFilter { snapshot: String },
+    #[error("Task {id} still running after {}s", timeout.as_secs_f64())]
+    TaskTimeout {
+        id: u64,
+        timeout: std::time::Duration,
+    },
+    #[error("Task {id} '{name}' failed: {output}")]
+    TaskFailed {
+        id: u64,
+        name: String,
+        output: String,
+    },
 }
 
 #[derive(Debug, Clone)]
 pub struct AptlyRest {
     client: reqwest::Client,
     url: Url,
+    retry: RetryPolicy,
+    capabilities: Arc<OnceCell<Capabilities>>,
 }
 
 impl AptlyRest {
+    /// Connect to aptly at `url`, which may also be a `unix:///path` socket
+    /// location
     pub fn new(url: Url) -> Self {
-        Self {
-            client: reqwest::Client::new(),
-            url,
-        }
+        Self::builder(url)
+            .build()
+            .expect("Failed to set up the HTTP client")
     }
 
     pub fn new_with_token(url: Url, token: &str) -> Result<Self, AptlyRestError> {
-        let mut headers = header::HeaderMap::new();
-        headers.insert(header::AUTHORIZATION, format!("Bearer {token}").parse()?);
+        Self::builder(url).token(token.to_owned()).build()
+    }
 
-        Ok(Self {
-            client: reqwest::ClientBuilder::new()
-                .default_headers(headers)
-                .build()?,
-            url,
-        })
+    /// Configure authentication, TLS, proxies and timeouts of the client
+    pub fn builder(url: Url) -> AptlyRestBuilder {
+        AptlyRestBuilder::new(url)
     }
 
-    pub async fn version(&self) -> Result<String, AptlyRestError> {
-        let mut url = self.url.clone();
-        url.path_segments_mut().unwrap().extend(&["api", "version"]);
+    /// Replace the [RetryPolicy] used for all requests
+    pub fn with_retry_policy(self, retry: RetryPolicy) -> Self {
+        Self { retry, ..self }
+    }
 
-        let r = self.client.get(url).send().await?.error_for_status()?;
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
 
-        #[derive(Deserialize)]
-        #[serde(rename_all = "PascalCase")]
-        struct Version {
-            version: String,
+    /// The version of the server; it's only requested once per client
+    pub async fn version(&self) -> Result<AptlyVersion, AptlyRestError> {
+        Ok(self.capabilities().await?.version().clone())
+    }
+
+    /// The API features the server has, based on its version
+    pub async fn capabilities(&self) -> Result<&Capabilities, AptlyRestError> {
+        self.capabilities
+            .get_or_try_init(|| async {
+                #[derive(Deserialize)]
+                #[serde(rename_all = "PascalCase")]
+                struct Version {
+                    version: String,
+                }
+
+                let v: Version = self.get(self.url(["api", "version"])).await?;
+                Ok(Capabilities::for_version(v.version.parse()?))
+            })
+            .await
+    }
+
+    /// Fail with [AptlyRestError::Unsupported] if the server doesn't have
+    /// `capability`
+    pub async fn require(&self, capability: Capability) -> Result<(), AptlyRestError> {
+        let capabilities = self.capabilities().await?;
+        if capabilities.supports(capability) {
+            Ok(())
+        } else {
+            Err(AptlyRestError::Unsupported {
+                capability,
+                version: capabilities.version().clone(),
+            })
         }
-        let v: Version = r.json().await?;
-        Ok(v.version)
     }
 
     pub async fn db_cleanup(&self) -> Result<(), AptlyRestError> {
@@ -71,6 +164,45 @@ impl AptlyRest {
         Ok(())
     }
 
+    /// Recover the database after a crash left it corrupted
+    pub async fn db_recover(&self) -> Result<(), AptlyRestError> {
+        let url = self.url(&["api", "db", "recover"]);
+        self.post::<()>(url).await?;
+        Ok(())
+    }
+
+    /// Like [AptlyRest::db_recover], but run as an aptly background task
+    pub async fn db_recover_async(&self) -> Result<TaskApi<'_>, AptlyRestError> {
+        let url = Self::async_url(self.url(&["api", "db", "recover"]));
+        self.task_request(self.client.post(url)).await
+    }
+
+    /// Disk usage of the storage aptly keeps its pool and database in
+    pub async fn storage(&self) -> Result<Storage, AptlyRestError> {
+        self.get(self.url(&["api", "storage"])).await
+    }
+
+    /// Whether the server is up; unlike other requests this is never
+    /// retried, so it reflects the state at the time of the call
+    pub async fn healthy(&self) -> Result<bool, AptlyRestError> {
+        self.probe(self.url(&["api", "healthy"])).await
+    }
+
+    /// Whether the server is ready to handle requests; like
+    /// [AptlyRest::healthy] this is never retried
+    pub async fn ready(&self) -> Result<bool, AptlyRestError> {
+        self.probe(self.url(&["api", "ready"])).await
+    }
+
+    /// Metrics of the server, which only has them if its
+    /// `enableMetricsEndpoint` option is set
+    pub async fn metrics(&self) -> Result<Metrics, AptlyRestError> {
+        let response = self
+            .send_request(self.client.get(self.url(&["api", "metrics"])))
+            .await?;
+        Ok(response.text().await?.parse()?)
+    }
+
     pub async fn repos(&self) -> Result<Vec<Repo>, AptlyRestError> {
         let url = self.url(&["api", "repos"]);
         self.get(url).await
@@ -88,15 +220,59 @@ impl AptlyRest {
         }
     }
 
+    pub async fn mirrors(&self) -> Result<Vec<Mirror>, AptlyRestError> {
+        let url = self.url(&["api", "mirrors"]);
+        self.get(url).await
+    }
+
+    pub async fn create_mirror(
+        &self,
+        name: &str,
+        archive_url: &Url,
+        options: &api::mirrors::CreateOptions,
+    ) -> Result<Mirror, AptlyRestError> {
+        #[derive(Debug, Clone, Serialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct CreateRequest<'a> {
+            name: &'a str,
+            #[serde(rename = "ArchiveURL")]
+            archive_url: &'a str,
+            #[serde(flatten)]
+            options: &'a api::mirrors::CreateOptions,
+        }
+
+        let url = self.url(&["api", "mirrors"]);
+        self.post_body(
+            url,
+            &CreateRequest {
+                name,
+                archive_url: archive_url.as_str(),
+                options,
+            },
+        )
+        .await
+    }
+
+    pub fn mirror<S: Into<String>>(&self, name: S) -> MirrorApi<'_> {
+        MirrorApi {
+            aptly: self,
+            name: name.into(),
+        }
+    }
+
     pub fn files(&self) -> FilesApi<'_> {
         FilesApi { aptly: self }
     }
 
+    pub fn gpg(&self) -> GpgApi<'_> {
+        GpgApi { aptly: self }
+    }
+
     pub fn packages(&self) -> PackagesApi<'_> {
         PackagesApi { aptly: self }
     }
 
-    pub fn publish_prefix<S: Into<String>>(&self, prefix: S) -> PublishApi<'_> {
+    pub fn publish_prefix<P: Into<PublishPrefix>>(&self, prefix: P) -> PublishApi<'_> {
         PublishApi {
             aptly: self,
             prefix: prefix.into(),
@@ -120,6 +296,89 @@ impl AptlyRest {
         self.get(url).await
     }
 
+    /// Create a snapshot containing exactly the given packages
+    pub async fn create_snapshot<'r, R>(
+        &self,
+        name: &str,
+        package_refs: R,
+        options: &api::snapshots::CreateOptions,
+    ) -> Result<Snapshot, AptlyRestError>
+    where
+        R: IntoIterator<Item = &'r AptlyKey>,
+    {
+        #[derive(Debug, Clone, Serialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct CreateRequest<'a, 'r> {
+            name: &'a str,
+            package_refs: Vec<&'r AptlyKey>,
+            #[serde(flatten)]
+            options: &'a api::snapshots::CreateOptions,
+        }
+
+        let url = self.url(&["api", "snapshots"]);
+        self.post_body(
+            url,
+            &CreateRequest {
+                name,
+                package_refs: package_refs.into_iter().collect(),
+                options,
+            },
+        )
+        .await
+    }
+
+    /// Create a snapshot by merging the given source snapshots, in order
+    pub async fn merge_snapshots(
+        &self,
+        destination: &str,
+        sources: &[String],
+        options: &api::snapshots::MergeOptions,
+    ) -> Result<Snapshot, AptlyRestError> {
+        #[derive(Debug, Clone, Serialize)]
+        #[serde(rename_all = "PascalCase")]
+        struct MergeRequest<'a> {
+            sources: &'a [String],
+        }
+
+        self.require(Capability::SnapshotMerge).await?;
+
+        let mut url = self.url(&["api", "snapshots", destination, "merge"]);
+        {
+            let mut pairs = url.query_pairs_mut();
+            if options.latest {
+                pairs.append_pair("latest", "1");
+            }
+            if options.no_remove {
+                pairs.append_pair("no-remove", "1");
+            }
+        }
+
+        self.post_body(url, &MergeRequest { sources }).await
+    }
+
+    pub async fn tasks(&self) -> Result<Vec<Task>, AptlyRestError> {
+        let url = self.url(&["api", "tasks"]);
+        self.get(url).await
+    }
+
+    pub fn task(&self, id: u64) -> TaskApi<'_> {
+        TaskApi { aptly: self, id }
+    }
+
+    /// Remove all finished tasks from the task list
+    pub async fn tasks_clear(&self) -> Result<(), AptlyRestError> {
+        let url = self.url(&["api", "tasks-clear"]);
+        self.send_request(self.client.post(url)).await?;
+        Ok(())
+    }
+
+    /// Wait until all currently queued tasks have finished
+    pub async fn tasks_wait(&self) -> Result<(), AptlyRestError> {
+        let url = self.url(&["api", "tasks-wait"]);
+        self.send_request(self.client.get(url)).await?;
+        Ok(())
+    }
+
     fn url<I>(&self, parts: I) -> Url
     where
         I: IntoIterator,
@@ -137,6 +396,26 @@ impl AptlyRest {
         self.json_request(self.client.get(url)).await
     }
 
+    /// Stream the elements of a JSON array response as they arrive, rather
+    /// than buffering the whole response
+    fn get_stream<T>(&self, url: Url) -> BoxStream<'static, Result<T, AptlyRestError>>
+    where
+        T: serde::de::DeserializeOwned + Send + 'static,
+    {
+        let aptly = self.clone();
+        async move {
+            let response = aptly.send_request(aptly.client.get(url)).await?;
+            Ok(json_stream::array(response.bytes_stream()))
+        }
+        .try_flatten_stream()
+        .boxed()
+    }
+
+    fn async_url(mut url: Url) -> Url {
+        url.query_pairs_mut().append_pair("_async", "true");
+        url
+    }
+
     async fn post<T>(&self, url: Url) -> Result<T, AptlyRestError>
     where
         T: serde::de::DeserializeOwned,
@@ -170,7 +449,71 @@ impl AptlyRest {
         &self,
         req: reqwest::RequestBuilder,
     ) -> Result<reqwest::Response, AptlyRestError> {
-        Ok(req.send().await?.error_for_status()?)
+        let mut request = req.build()?;
+        let mut retry = 0;
+        let response = loop {
+            // Streamed bodies can't be replayed, so those get a single attempt
+            let next = request.try_clone();
+            let method = request.method().clone();
+            let result = self.client.execute(request).await;
+
+            let next = match next {
+                Some(next) if retry + 1 < self.retry.max_attempts => next,
+                _ => break result?,
+            };
+            let retriable = match &result {
+                Ok(response) => self.retry.retry_status(&method, response.status()),
+                Err(e) => self.retry.retry_error(&method, e),
+            };
+            if !retriable {
+                break result?;
+            }
+
+            retry += 1;
+            tokio::time::sleep(self.retry.interval(retry)).await;
+            request = next;
+        };
+
+        Self::check_status(response).await
+    }
+
+    /// Turn an unsuccessful response into the matching error
+    async fn check_status(
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, AptlyRestError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let message = error_message(&response.text().await?);
+        Err(match status {
+            StatusCode::NOT_FOUND => AptlyRestError::NotFound { message },
+            StatusCode::CONFLICT => AptlyRestError::Conflict { message },
+            StatusCode::BAD_REQUEST => AptlyRestError::BadRequest { message },
+            s if s.is_server_error() => AptlyRestError::Server { status, message },
+            _ => AptlyRestError::Status { status, message },
+        })
+    }
+
+    /// A single request that counts server errors as a negative answer
+    async fn probe(&self, url: Url) -> Result<bool, AptlyRestError> {
+        let response = self.client.get(url).send().await?;
+        if response.status().is_server_error() {
+            return Ok(false);
+        }
+        Self::check_status(response).await?;
+        Ok(true)
+    }
+
+    /// Submit the request as a background task and return a handle to it
+    async fn task_request(
+        &self,
+        req: reqwest::RequestBuilder,
+    ) -> Result<TaskApi<'_>, AptlyRestError> {
+        self.require(Capability::AsyncTasks).await?;
+        let task: Task = self.json_request(req).await?;
+        Ok(self.task(task.id()))
     }
 
     async fn json_request<T>(&self, req: reqwest::RequestBuilder) -> Result<T, AptlyRestError>
@@ -180,3 +523,29 @@ impl AptlyRest {
         Ok(self.send_request(req).await?.json().await?)
     }
 }
+
+/// Extract the message from an aptly error body, which is either
+/// `{"error": ...}` or a list of those.
+fn error_message(body: &str) -> String {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        error: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ErrorResponse {
+        Single(ErrorBody),
+        List(Vec<ErrorBody>),
+    }
+
+    match serde_json::from_str(body) {
+        Ok(ErrorResponse::Single(e)) => e.error,
+        Ok(ErrorResponse::List(errors)) => errors
+            .into_iter()
+            .map(|e| e.error)
+            .collect::<Vec<_>>()
+            .join("; "),
+        Err(_) => body.trim().to_owned(),
+    }
+}
@@ -0,0 +1,367 @@
+//! Parsing of the Prometheus text format served by `/api/metrics`
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use serde::Serialize;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid metrics on line {line}: {message}")]
+pub struct MetricsParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricType {
+    Counter,
+    Gauge,
+    Histogram,
+    Summary,
+    Untyped,
+}
+
+impl FromStr for MetricType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "counter" => Ok(MetricType::Counter),
+            "gauge" => Ok(MetricType::Gauge),
+            "histogram" => Ok(MetricType::Histogram),
+            "summary" => Ok(MetricType::Summary),
+            "untyped" => Ok(MetricType::Untyped),
+            _ => Err(format!("unknown metric type '{s}'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sample {
+    name: String,
+    labels: BTreeMap<String, String>,
+    value: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timestamp: Option<i64>,
+}
+
+impl Sample {
+    /// The name of the sample, which for histograms and summaries has a
+    /// `_bucket`, `_sum` or `_count` suffix on the name of the family
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn labels(&self) -> &BTreeMap<String, String> {
+        &self.labels
+    }
+
+    pub fn label(&self, name: &str) -> Option<&str> {
+        self.labels.get(name).map(String::as_str)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Milliseconds since the epoch, if the sample has an explicit timestamp
+    pub fn timestamp(&self) -> Option<i64> {
+        self.timestamp
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricFamily {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<String>,
+    #[serde(rename = "type")]
+    type_: MetricType,
+    samples: Vec<Sample>,
+}
+
+impl MetricFamily {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            help: None,
+            type_: MetricType::Untyped,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
+    pub fn type_(&self) -> MetricType {
+        self.type_
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    fn has_sample(&self, name: &str) -> bool {
+        match name.strip_prefix(self.name.as_str()) {
+            Some("") => true,
+            Some("_bucket") => self.type_ == MetricType::Histogram,
+            Some("_sum" | "_count") => {
+                matches!(self.type_, MetricType::Histogram | MetricType::Summary)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Metrics as exposed in the Prometheus text format
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Metrics {
+    families: Vec<MetricFamily>,
+}
+
+impl Metrics {
+    pub fn families(&self) -> &[MetricFamily] {
+        &self.families
+    }
+
+    pub fn family(&self, name: &str) -> Option<&MetricFamily> {
+        self.families.iter().find(|f| f.name == name)
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &Sample> {
+        self.families.iter().flat_map(|f| f.samples.iter())
+    }
+
+    /// The value of the first sample with the given name that has all of
+    /// the given labels
+    pub fn value(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+        self.samples()
+            .find(|s| s.name == name && labels.iter().all(|(k, v)| s.label(k) == Some(*v)))
+            .map(|s| s.value)
+    }
+
+    fn family_mut(&mut self, name: &str) -> &mut MetricFamily {
+        let index = match self.families.iter().position(|f| f.name == name) {
+            Some(index) => index,
+            None => {
+                self.families.push(MetricFamily::new(name));
+                self.families.len() - 1
+            }
+        };
+        &mut self.families[index]
+    }
+}
+
+impl FromStr for Metrics {
+    type Err = MetricsParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut metrics = Metrics::default();
+
+        for (i, line) in s.lines().enumerate() {
+            let error = |message: String| MetricsParseError {
+                line: i + 1,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(comment) = line.strip_prefix('#') {
+                let mut parts = comment.trim_start().splitn(3, [' ', '\t']);
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some("HELP"), Some(name), help) => {
+                        metrics.family_mut(name).help = Some(unescape(help.unwrap_or_default()));
+                    }
+                    (Some("TYPE"), Some(name), Some(type_)) => {
+                        metrics.family_mut(name).type_ = type_.trim().parse().map_err(error)?;
+                    }
+                    // Any other comment
+                    _ => (),
+                }
+                continue;
+            }
+
+            let sample = parse_sample(line).map_err(error)?;
+            let family = match metrics.families.last_mut() {
+                Some(family) if family.has_sample(&sample.name) => family,
+                _ => metrics.family_mut(&sample.name),
+            };
+            family.samples.push(sample);
+        }
+
+        Ok(metrics)
+    }
+}
+
+fn parse_sample(line: &str) -> Result<Sample, String> {
+    let name_end = line
+        .find(|c: char| c == '{' || c.is_ascii_whitespace())
+        .ok_or("missing value")?;
+    let (name, mut rest) = line.split_at(name_end);
+    if name.is_empty() {
+        return Err("missing metric name".to_owned());
+    }
+
+    let mut labels = BTreeMap::new();
+    if let Some(mut l) = rest.strip_prefix('{') {
+        loop {
+            l = l.trim_start();
+            if let Some(r) = l.strip_prefix('}') {
+                rest = r;
+                break;
+            }
+            let (label, r) = l.split_once('=').ok_or("missing '=' after label name")?;
+            let r = r
+                .trim_start()
+                .strip_prefix('"')
+                .ok_or("missing '\"' before label value")?;
+            let (value, r) = label_value(r)?;
+            labels.insert(label.trim().to_owned(), value);
+
+            l = r.trim_start();
+            l = l.strip_prefix(',').unwrap_or(l);
+        }
+    }
+
+    let mut parts = rest.split_ascii_whitespace();
+    let value = parts.next().ok_or("missing value")?;
+    let value = value
+        .parse()
+        .map_err(|_| format!("invalid value '{value}'"))?;
+    let timestamp = parts
+        .next()
+        .map(|t| t.parse().map_err(|_| format!("invalid timestamp '{t}'")))
+        .transpose()?;
+    if parts.next().is_some() {
+        return Err("trailing data after timestamp".to_owned());
+    }
+
+    Ok(Sample {
+        name: name.to_owned(),
+        labels,
+        value,
+        timestamp,
+    })
+}
+
+/// Read a label value up to its closing quote, returning the unescaped value
+/// and the remainder of the line
+fn label_value(s: &str) -> Result<(String, &str), String> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Ok((unescape(&s[..i]), &s[i + 1..])),
+            _ => (),
+        }
+    }
+    Err("unterminated label value".to_owned())
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some(c) => out.push(c),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const METRICS: &str = r#"# HELP aptly_api_http_requests_in_flight Number of concurrent HTTP api requests currently handled.
+# TYPE aptly_api_http_requests_in_flight gauge
+aptly_api_http_requests_in_flight{method="GET",path="/api/version"} 1
+# HELP aptly_api_http_requests_total Total number of api requests.
+# TYPE aptly_api_http_requests_total counter
+aptly_api_http_requests_total{code="200",method="GET",path="/api/version"} 12
+aptly_api_http_requests_total{code="404",method="GET",path="/api/repos/:name"} 3 1700000000000
+# HELP aptly_api_http_request_duration_seconds Duration of api requests in seconds.
+# TYPE aptly_api_http_request_duration_seconds histogram
+aptly_api_http_request_duration_seconds_bucket{code="200",le="0.1"} 10
+aptly_api_http_request_duration_seconds_bucket{code="200",le="+Inf"} 12
+aptly_api_http_request_duration_seconds_sum{code="200"} 0.25
+aptly_api_http_request_duration_seconds_count{code="200"} 12
+aptly_build_info{version="1.6.0",goversion="go1.22 \"x\"\\y"} 1
+"#;
+
+    #[test]
+    fn parse() {
+        let metrics: Metrics = METRICS.parse().unwrap();
+        assert_eq!(4, metrics.families().len());
+
+        let in_flight = metrics.family("aptly_api_http_requests_in_flight").unwrap();
+        assert_eq!(MetricType::Gauge, in_flight.type_());
+        assert_eq!(
+            Some("Number of concurrent HTTP api requests currently handled."),
+            in_flight.help()
+        );
+        assert_eq!(1, in_flight.samples().len());
+
+        assert_eq!(
+            Some(3.0),
+            metrics.value("aptly_api_http_requests_total", &[("code", "404")])
+        );
+        let total = metrics.family("aptly_api_http_requests_total").unwrap();
+        assert_eq!(Some(1700000000000), total.samples()[1].timestamp());
+
+        let duration = metrics
+            .family("aptly_api_http_request_duration_seconds")
+            .unwrap();
+        assert_eq!(MetricType::Histogram, duration.type_());
+        assert_eq!(4, duration.samples().len());
+        assert_eq!(
+            Some(12.0),
+            metrics.value(
+                "aptly_api_http_request_duration_seconds_bucket",
+                &[("le", "+Inf")]
+            )
+        );
+        assert_eq!(
+            Some(0.25),
+            metrics.value("aptly_api_http_request_duration_seconds_sum", &[])
+        );
+
+        let build = metrics.family("aptly_build_info").unwrap();
+        assert_eq!(MetricType::Untyped, build.type_());
+        assert_eq!(
+            Some("go1.22 \"x\"\\y"),
+            build.samples()[0].label("goversion")
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = "# TYPE a gauge\na{b=\"c} 1".parse::<Metrics>().unwrap_err();
+        assert_eq!(2, err.line);
+        assert!("a".parse::<Metrics>().is_err());
+        assert!("a x".parse::<Metrics>().is_err());
+        assert!("a{b} 1".parse::<Metrics>().is_err());
+        assert!("a 1 2 3".parse::<Metrics>().is_err());
+        assert!("# TYPE a meter".parse::<Metrics>().is_err());
+        assert!("# some comment\n\na 1".parse::<Metrics>().is_ok());
+
+        let metrics: Metrics = "a -Inf\nb NaN".parse().unwrap();
+        assert_eq!(Some(f64::NEG_INFINITY), metrics.value("a", &[]));
+        assert!(metrics.value("b", &[]).unwrap().is_nan());
+    }
+}
@@ -0,0 +1,270 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+/// Where aptly stores a publish: its local public directory or one of the
+/// `FileSystemPublishEndpoints`, `S3PublishEndpoints`, `SwiftPublishEndpoints`
+/// or `AzurePublishEndpoints` in its configuration
+#[derive(
+    Debug,
+    Clone,
+    Default,
+    PartialEq,
+    Eq,
+    Ord,
+    PartialOrd,
+    Hash,
+    DeserializeFromStr,
+    SerializeDisplay,
+)]
+pub enum StorageEndpoint {
+    /// aptly's own public directory
+    #[default]
+    Local,
+    Filesystem(String),
+    S3(String),
+    Swift(String),
+    Azure(String),
+}
+
+impl StorageEndpoint {
+    /// The name of the endpoint in aptly's configuration, `None` for the
+    /// local storage
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            StorageEndpoint::Local => None,
+            StorageEndpoint::Filesystem(name)
+            | StorageEndpoint::S3(name)
+            | StorageEndpoint::Swift(name)
+            | StorageEndpoint::Azure(name) => Some(name),
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        *self == StorageEndpoint::Local
+    }
+}
+
+impl Display for StorageEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageEndpoint::Local => Ok(()),
+            StorageEndpoint::Filesystem(name) => write!(f, "filesystem:{name}"),
+            StorageEndpoint::S3(name) => write!(f, "s3:{name}"),
+            StorageEndpoint::Swift(name) => write!(f, "swift:{name}"),
+            StorageEndpoint::Azure(name) => write!(f, "azure:{name}"),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum PrefixParseError {
+    #[error("Unknown storage type '{0}'")]
+    UnknownStorage(String),
+    #[error("Missing name of the {0} storage endpoint")]
+    MissingEndpoint(String),
+}
+
+impl FromStr for StorageEndpoint {
+    type Err = PrefixParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Ok(StorageEndpoint::Local);
+        }
+
+        let (kind, name) = s
+            .split_once(':')
+            .ok_or_else(|| PrefixParseError::UnknownStorage(s.to_owned()))?;
+        if name.is_empty() {
+            return Err(PrefixParseError::MissingEndpoint(kind.to_owned()));
+        }
+        let name = name.to_owned();
+        match kind {
+            "filesystem" => Ok(StorageEndpoint::Filesystem(name)),
+            "s3" => Ok(StorageEndpoint::S3(name)),
+            "swift" => Ok(StorageEndpoint::Swift(name)),
+            "azure" => Ok(StorageEndpoint::Azure(name)),
+            _ => Err(PrefixParseError::UnknownStorage(kind.to_owned())),
+        }
+    }
+}
+
+/// The location of a publish: a path within a storage endpoint, written as
+/// `[<storage>:<endpoint>:]<path>` e.g. `apertis`, `s3:bucket:debian` or
+/// `filesystem:public:.`
+#[derive(
+    Debug, Clone, PartialEq, Eq, Ord, PartialOrd, Hash, DeserializeFromStr, SerializeDisplay,
+)]
+pub struct PublishPrefix {
+    storage: StorageEndpoint,
+    path: String,
+}
+
+impl PublishPrefix {
+    /// A prefix in aptly's local storage; an empty path or `.` is the root
+    pub fn new(path: impl AsRef<str>) -> Self {
+        Self::with_storage(StorageEndpoint::Local, path)
+    }
+
+    pub fn with_storage(storage: StorageEndpoint, path: impl AsRef<str>) -> Self {
+        let path = path.as_ref().trim_matches('/');
+        Self {
+            storage,
+            path: if path.is_empty() { "." } else { path }.to_owned(),
+        }
+    }
+
+    pub fn storage(&self) -> &StorageEndpoint {
+        &self.storage
+    }
+
+    /// The path within the storage, `.` for its root
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.path == "."
+    }
+
+    /// The prefix as a single URL path segment for the publish API. aptly
+    /// unescapes `_` to `/` and `__` to `_` in the whole segment, including
+    /// the storage; the root of the local storage is written as `:.` as a lone
+    /// `.` segment would be normalized away.
+    pub fn url_segment(&self) -> String {
+        let segment = if self.storage.is_local() && !self.is_root() {
+            self.path.clone()
+        } else {
+            format!("{}:{}", self.storage, self.path)
+        };
+        segment.replace('_', "__").replace('/', "_")
+    }
+}
+
+impl From<&PublishPrefix> for PublishPrefix {
+    fn from(prefix: &PublishPrefix) -> Self {
+        prefix.clone()
+    }
+}
+
+/// Parses the prefix like [`FromStr`], but keeps a prefix with an unknown
+/// storage as a local path so aptly reports it rather than the conversion
+impl From<&str> for PublishPrefix {
+    fn from(s: &str) -> Self {
+        s.parse().unwrap_or_else(|_| Self::new(s))
+    }
+}
+
+impl From<String> for PublishPrefix {
+    fn from(s: String) -> Self {
+        s.as_str().into()
+    }
+}
+
+impl From<&String> for PublishPrefix {
+    fn from(s: &String) -> Self {
+        s.as_str().into()
+    }
+}
+
+impl Display for PublishPrefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.storage.is_local() {
+            write!(f, "{}", self.path)
+        } else {
+            write!(f, "{}:{}", self.storage, self.path)
+        }
+    }
+}
+
+impl FromStr for PublishPrefix {
+    type Err = PrefixParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Like aptly, everything up to the last colon is the storage
+        match s.rsplit_once(':') {
+            Some((storage, path)) => Ok(Self::with_storage(storage.parse()?, path)),
+            None => Ok(Self::new(s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let p: PublishPrefix = "apertis".parse().unwrap();
+        assert_eq!(PublishPrefix::new("apertis"), p);
+        assert_eq!("apertis", p.to_string());
+
+        let p: PublishPrefix = "s3:bucket:debian/main/".parse().unwrap();
+        assert_eq!(&StorageEndpoint::S3("bucket".to_owned()), p.storage());
+        assert_eq!("debian/main", p.path());
+        assert_eq!("s3:bucket:debian/main", p.to_string());
+
+        let p: PublishPrefix = "filesystem:public:".parse().unwrap();
+        assert_eq!(Some("public"), p.storage().name());
+        assert!(p.is_root());
+        assert_eq!("filesystem:public:.", p.to_string());
+
+        assert_eq!(PublishPrefix::new("."), "".parse().unwrap());
+        assert_eq!(PublishPrefix::new("."), ":.".parse().unwrap());
+        assert_eq!(
+            StorageEndpoint::Swift("store".to_owned()),
+            *"swift:store:x".parse::<PublishPrefix>().unwrap().storage()
+        );
+
+        assert_eq!(
+            Err(PrefixParseError::UnknownStorage("ftp".to_owned())),
+            "ftp:host:path".parse::<PublishPrefix>()
+        );
+        assert_eq!(
+            Err(PrefixParseError::MissingEndpoint("s3".to_owned())),
+            "s3::path".parse::<PublishPrefix>()
+        );
+        assert!("bucket:path".parse::<PublishPrefix>().is_err());
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(PublishPrefix::new("apertis"), "apertis".into());
+        assert_eq!(
+            PublishPrefix::with_storage(StorageEndpoint::S3("bucket".to_owned()), "debian"),
+            String::from("s3:bucket:debian").into()
+        );
+        let p = PublishPrefix::from("bucket:path");
+        assert!(p.storage().is_local());
+        assert_eq!("bucket:path", p.path());
+    }
+
+    #[test]
+    fn url_segment() {
+        assert_eq!("apertis", PublishPrefix::new("apertis").url_segment());
+        assert_eq!(":.", PublishPrefix::new(".").url_segment());
+        assert_eq!(
+            "apertis_v2024__dev",
+            PublishPrefix::new("apertis/v2024_dev").url_segment()
+        );
+        assert_eq!(
+            "s3:bucket:.",
+            "s3:bucket:".parse::<PublishPrefix>().unwrap().url_segment()
+        );
+        assert_eq!(
+            "filesystem:public:a_b",
+            "filesystem:public:a/b"
+                .parse::<PublishPrefix>()
+                .unwrap()
+                .url_segment()
+        );
+        assert_eq!(
+            "s3:my__bucket:debian_main",
+            "s3:my_bucket:debian/main"
+                .parse::<PublishPrefix>()
+                .unwrap()
+                .url_segment()
+        );
+    }
+}
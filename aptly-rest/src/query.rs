@@ -0,0 +1,677 @@
+//! Aptly package queries.
+//!
+//! [Query] is the syntax tree of aptly's
+//! [package query language](https://www.aptly.info/doc/feature/query/).
+//! Queries can be built up in code and formatted for the API, or parsed from
+//! user input to validate them before sending them to aptly:
+//!
+//! ```
+//! use aptly_rest::query::{Query, Relation};
+//!
+//! let query = Query::package("hello")
+//!     .or(Query::package_version("bash", Relation::GreaterOrEqual, "5.0"))
+//!     .and(Query::architecture("amd64"));
+//! assert_eq!(
+//!     "(hello | bash (>= 5.0)), $Architecture (= amd64)",
+//!     query.to_string()
+//! );
+//! assert_eq!(query, query.to_string().parse().unwrap());
+//! ```
+use std::{fmt::Display, str::FromStr};
+
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+/// Relation between a field and a value in a condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// `=`
+    Equal,
+    /// `<<`
+    Less,
+    /// `<=`, or the deprecated `<`
+    LessOrEqual,
+    /// `>>`
+    Greater,
+    /// `>=`, or the deprecated `>`
+    GreaterOrEqual,
+    /// `%`, shell-style pattern match
+    Pattern,
+    /// `~`, regular expression match
+    Regex,
+}
+
+impl Relation {
+    /// Operators in the order they have to be tried when parsing
+    const OPERATORS: [(&'static str, Relation); 9] = [
+        ("<<", Relation::Less),
+        ("<=", Relation::LessOrEqual),
+        (">>", Relation::Greater),
+        (">=", Relation::GreaterOrEqual),
+        ("<", Relation::LessOrEqual),
+        (">", Relation::GreaterOrEqual),
+        ("=", Relation::Equal),
+        ("%", Relation::Pattern),
+        ("~", Relation::Regex),
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Relation::Equal => "=",
+            Relation::Less => "<<",
+            Relation::LessOrEqual => "<=",
+            Relation::Greater => ">>",
+            Relation::GreaterOrEqual => ">=",
+            Relation::Pattern => "%",
+            Relation::Regex => "~",
+        }
+    }
+}
+
+impl Display for Relation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A condition on a field, e.g. the `(>= 1.0)` in `Version (>= 1.0)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub relation: Relation,
+    pub value: String,
+}
+
+impl Condition {
+    pub fn new(relation: Relation, value: impl Into<String>) -> Self {
+        Self {
+            relation,
+            value: value.into(),
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({} {})", self.relation, Quoted(&self.value))
+    }
+}
+
+/// A package field that can be queried
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    /// `$Architecture`
+    Architecture,
+    /// `$Source`, the source package name of a binary package
+    Source,
+    /// `$SourceVersion`
+    SourceVersion,
+    /// `$Version`
+    Version,
+    /// `$PackageType`: `source`, `deb` or `udeb`
+    PackageType,
+    /// A field of the package's control paragraph like `Priority` or `Name`;
+    /// it has to start with an uppercase letter.
+    Control(String),
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Field::Architecture => f.write_str("$Architecture"),
+            Field::Source => f.write_str("$Source"),
+            Field::SourceVersion => f.write_str("$SourceVersion"),
+            Field::Version => f.write_str("$Version"),
+            Field::PackageType => f.write_str("$PackageType"),
+            Field::Control(name) => Quoted(name).fmt(f),
+        }
+    }
+}
+
+/// An aptly package query
+#[derive(Debug, Clone, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
+pub enum Query {
+    /// Packages matching any of the queries, `a | b`
+    Or(Vec<Query>),
+    /// Packages matching all of the queries, `a, b`
+    And(Vec<Query>),
+    /// Packages not matching the query, `!a`
+    Not(Box<Query>),
+    /// Packages that have the field, or whose field matches the condition,
+    /// e.g. `Priority (required)`
+    Field {
+        field: Field,
+        condition: Option<Condition>,
+    },
+    /// Packages with the given name, optionally restricted like a dependency,
+    /// e.g. `bash (>= 5.0) {amd64}`
+    Package {
+        name: String,
+        condition: Option<Condition>,
+        architecture: Option<String>,
+    },
+    /// The one package with this name, version and architecture,
+    /// `name_version_arch`
+    Reference {
+        name: String,
+        version: String,
+        architecture: String,
+    },
+}
+
+impl Query {
+    /// Packages named `name`
+    pub fn package(name: impl Into<String>) -> Self {
+        Query::Package {
+            name: name.into(),
+            condition: None,
+            architecture: None,
+        }
+    }
+
+    /// Packages named `name` with a version in `relation` to `version`
+    pub fn package_version(
+        name: impl Into<String>,
+        relation: Relation,
+        version: impl Into<String>,
+    ) -> Self {
+        Query::Package {
+            name: name.into(),
+            condition: Some(Condition::new(relation, version)),
+            architecture: None,
+        }
+    }
+
+    pub fn reference(
+        name: impl Into<String>,
+        version: impl Into<String>,
+        architecture: impl Into<String>,
+    ) -> Self {
+        Query::Reference {
+            name: name.into(),
+            version: version.into(),
+            architecture: architecture.into(),
+        }
+    }
+
+    /// Packages that have `field`
+    pub fn has_field(field: Field) -> Self {
+        Query::Field {
+            field,
+            condition: None,
+        }
+    }
+
+    /// Packages whose `field` is in `relation` to `value`
+    pub fn field(field: Field, relation: Relation, value: impl Into<String>) -> Self {
+        Query::Field {
+            field,
+            condition: Some(Condition::new(relation, value)),
+        }
+    }
+
+    pub fn architecture(architecture: impl Into<String>) -> Self {
+        Self::field(Field::Architecture, Relation::Equal, architecture)
+    }
+
+    /// Binary packages built from the source package `source`
+    pub fn source(source: impl Into<String>) -> Self {
+        Self::field(Field::Source, Relation::Equal, source)
+    }
+
+    pub fn package_type(package_type: impl Into<String>) -> Self {
+        Self::field(Field::PackageType, Relation::Equal, package_type)
+    }
+
+    /// Every package
+    pub fn all() -> Self {
+        Self::has_field(Field::Control("Name".to_string()))
+    }
+
+    pub fn or(self, other: Query) -> Self {
+        match self {
+            Query::Or(mut queries) => {
+                queries.push(other);
+                Query::Or(queries)
+            }
+            q => Query::Or(vec![q, other]),
+        }
+    }
+
+    pub fn and(self, other: Query) -> Self {
+        match self {
+            Query::And(mut queries) => {
+                queries.push(other);
+                Query::And(queries)
+            }
+            q => Query::And(vec![q, other]),
+        }
+    }
+
+    /// For package-like queries, restrict them to an architecture as in
+    /// `bash {amd64}`; other queries are combined with an
+    /// `$Architecture` condition.
+    pub fn with_architecture(self, arch: impl Into<String>) -> Self {
+        match self {
+            Query::Package {
+                name,
+                condition,
+                architecture: None,
+            } => Query::Package {
+                name,
+                condition,
+                architecture: Some(arch.into()),
+            },
+            q => q.and(Query::architecture(arch)),
+        }
+    }
+
+    fn fmt_nested(&self, f: &mut std::fmt::Formatter<'_>, parenthesize: bool) -> std::fmt::Result {
+        if parenthesize {
+            write!(f, "({self})")
+        } else {
+            self.fmt(f)
+        }
+    }
+}
+
+impl std::ops::Not for Query {
+    type Output = Query;
+
+    fn not(self) -> Self::Output {
+        Query::Not(Box::new(self))
+    }
+}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Query::Or(queries) => {
+                for (i, q) in queries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    q.fmt_nested(f, false)?;
+                }
+                Ok(())
+            }
+            Query::And(queries) => {
+                for (i, q) in queries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    q.fmt_nested(f, matches!(q, Query::Or(_)))?;
+                }
+                Ok(())
+            }
+            Query::Not(q) => {
+                f.write_str("!")?;
+                q.fmt_nested(f, matches!(**q, Query::Or(_) | Query::And(_)))
+            }
+            Query::Field { field, condition } => {
+                field.fmt(f)?;
+                if let Some(condition) = condition {
+                    write!(f, " {condition}")?;
+                }
+                Ok(())
+            }
+            Query::Package {
+                name,
+                condition,
+                architecture,
+            } => {
+                Quoted(name).fmt(f)?;
+                if let Some(condition) = condition {
+                    write!(f, " {condition}")?;
+                }
+                if let Some(architecture) = architecture {
+                    write!(f, " {{{}}}", Quoted(architecture))?;
+                }
+                Ok(())
+            }
+            Query::Reference {
+                name,
+                version,
+                architecture,
+            } => write!(f, "{name}_{version}_{architecture}"),
+        }
+    }
+}
+
+/// Formats a string, quoting and escaping it if it isn't a plain word
+struct Quoted<'a>(&'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plain = !self.0.is_empty()
+            && !self.0.starts_with(|c| "<>=%~".contains(c))
+            && !self
+                .0
+                .contains(|c: char| c.is_whitespace() || is_special(c));
+        if plain {
+            return f.write_str(self.0);
+        }
+
+        f.write_str("\"")?;
+        for c in self.0.chars() {
+            if c == '"' || c == '\\' {
+                f.write_str("\\")?;
+            }
+            write!(f, "{c}")?;
+        }
+        f.write_str("\"")
+    }
+}
+
+fn is_special(c: char) -> bool {
+    "()|,!{}\"'\\".contains(c)
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid query at position {position}: {message}")]
+pub struct ParseError {
+    /// Byte offset into the query
+    pub position: usize,
+    pub message: String,
+}
+
+impl FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { input: s, pos: 0 };
+        let query = parser.or()?;
+        parser.skip_whitespace();
+        if parser.pos < s.len() {
+            return Err(parser.error("unexpected input"));
+        }
+        Ok(query)
+    }
+}
+
+/// Recursive descent parser for:
+///
+/// ```text
+/// or        := and ('|' and)*
+/// and       := not (',' not)*
+/// not       := '!' not | '(' or ')' | term
+/// term      := field condition? | name condition? ('{' arch '}')?
+///            | name '_' version '_' arch
+/// condition := '(' operator? value ')'
+/// ```
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            position: self.pos,
+            message: message.to_owned(),
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skip whitespace and consume `token` if it's next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), ParseError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{token}'")))
+        }
+    }
+
+    fn or(&mut self) -> Result<Query, ParseError> {
+        let mut queries = vec![self.and()?];
+        while self.eat("|") {
+            queries.push(self.and()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.pop().unwrap()
+        } else {
+            Query::Or(queries)
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, ParseError> {
+        let mut queries = vec![self.not()?];
+        while self.eat(",") {
+            queries.push(self.not()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.pop().unwrap()
+        } else {
+            Query::And(queries)
+        })
+    }
+
+    fn not(&mut self) -> Result<Query, ParseError> {
+        if self.eat("!") {
+            Ok(!self.not()?)
+        } else if self.eat("(") {
+            let query = self.or()?;
+            self.expect(")")?;
+            Ok(query)
+        } else {
+            self.term()
+        }
+    }
+
+    fn term(&mut self) -> Result<Query, ParseError> {
+        let start = self.pos;
+        let word = self.string()?;
+
+        if let Some(special) = word.strip_prefix('$') {
+            let field = match special {
+                "Architecture" => Field::Architecture,
+                "Source" => Field::Source,
+                "SourceVersion" => Field::SourceVersion,
+                "Version" => Field::Version,
+                "PackageType" => Field::PackageType,
+                _ => {
+                    return Err(ParseError {
+                        position: start,
+                        message: format!("unknown special field '{word}'"),
+                    })
+                }
+            };
+            let condition = self.condition()?;
+            return Ok(Query::Field { field, condition });
+        }
+
+        if word.starts_with(|c: char| c.is_uppercase()) {
+            let condition = self.condition()?;
+            return Ok(Query::Field {
+                field: Field::Control(word),
+                condition,
+            });
+        }
+
+        if word.contains('_') {
+            let mut parts = word.split('_');
+            return match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(name), Some(version), Some(architecture), None)
+                    if !name.is_empty() && !version.is_empty() && !architecture.is_empty() =>
+                {
+                    Ok(Query::reference(name, version, architecture))
+                }
+                _ => Err(ParseError {
+                    position: start,
+                    message: format!("invalid package reference '{word}'"),
+                }),
+            };
+        }
+
+        let condition = self.condition()?;
+        let architecture = if self.eat("{") {
+            let architecture = self.string()?;
+            self.expect("}")?;
+            Some(architecture)
+        } else {
+            None
+        };
+        Ok(Query::Package {
+            name: word,
+            condition,
+            architecture,
+        })
+    }
+
+    fn condition(&mut self) -> Result<Option<Condition>, ParseError> {
+        if !self.eat("(") {
+            return Ok(None);
+        }
+
+        self.skip_whitespace();
+        let relation = Relation::OPERATORS
+            .iter()
+            .find(|(op, _)| self.rest().starts_with(op))
+            .map(|(op, relation)| {
+                self.pos += op.len();
+                *relation
+            })
+            .unwrap_or(Relation::Equal);
+        let value = self.string()?;
+        self.expect(")")?;
+
+        Ok(Some(Condition { relation, value }))
+    }
+
+    /// A plain word or a single or double quoted string
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.skip_whitespace();
+        let mut chars = self.rest().char_indices();
+
+        match chars.next() {
+            Some((_, quote @ ('"' | '\''))) => {
+                let mut value = String::new();
+                let mut escaped = false;
+                for (i, c) in chars {
+                    if escaped {
+                        value.push(c);
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == quote {
+                        self.pos += i + 1;
+                        return Ok(value);
+                    } else {
+                        value.push(c);
+                    }
+                }
+                Err(self.error("unterminated string"))
+            }
+            Some((_, c)) if !c.is_whitespace() && !is_special(c) => {
+                let rest = self.rest();
+                let end = rest
+                    .find(|c: char| c.is_whitespace() || is_special(c))
+                    .unwrap_or(rest.len());
+                let word = rest[..end].to_owned();
+                self.pos += end;
+                Ok(word)
+            }
+            _ => Err(self.error("expected a package name, field or value")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let query: Query = "Priority (required) | !$Architecture (amd64), bash (>= 5.0) {arm64}"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            Query::field(
+                Field::Control("Priority".to_string()),
+                Relation::Equal,
+                "required"
+            )
+            .or((!Query::architecture("amd64")).and(
+                Query::package_version("bash", Relation::GreaterOrEqual, "5.0")
+                    .with_architecture("arm64")
+            )),
+            query
+        );
+
+        let query: Query = "(Name (~ \"^lib.*\\\\d\"), !hello_2.10-2_amd64)"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            Query::field(
+                Field::Control("Name".to_string()),
+                Relation::Regex,
+                "^lib.*\\d"
+            )
+            .and(!Query::reference("hello", "2.10-2", "amd64")),
+            query
+        );
+
+        assert_eq!(
+            Query::package_version("bash", Relation::LessOrEqual, "5"),
+            "bash (<5)".parse().unwrap()
+        );
+        assert_eq!(
+            Query::package_type("source"),
+            "$PackageType ( 'source' )".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (query, position) in [
+            ("", 0),
+            ("bash (>= 5.0", 12),
+            ("a | ", 4),
+            ("$Foo", 0),
+            ("hello_1.0", 0),
+            ("Name (~ \"x)", 8),
+            ("a b", 2),
+        ] {
+            let err = query.parse::<Query>().unwrap_err();
+            assert_eq!(position, err.position, "{query}: {err}");
+        }
+    }
+
+    #[test]
+    fn display() {
+        let query = (!Query::package("a").or(Query::package("b"))).and(Query::package("c").or(
+            Query::field(
+                Field::Control("Description".to_string()),
+                Relation::Pattern,
+                "*say \"hello\", world*",
+            ),
+        ));
+        let formatted = query.to_string();
+        assert_eq!(
+            "!(a | b), (c | Description (% \"*say \\\"hello\\\", world*\"))",
+            formatted
+        );
+        assert_eq!(query, formatted.parse().unwrap());
+
+        assert_eq!(
+            "$Source (= \">=weird\")",
+            Query::source(">=weird").to_string()
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use reqwest::{Method, StatusCode};
+
+/// When and how often [crate::AptlyRest] retries failed requests.
+///
+/// Requests are only retried if their method is in `methods`, except for
+/// connection failures where the request never reached aptly, which are
+/// always safe to retry. By default only `GET`, `HEAD` and `OPTIONS` are
+/// retried: aptly's `PUT`s, e.g. a publish update that timed out at a proxy,
+/// may still be running when sent again, and a `DELETE` repeated after a lost
+/// response fails with not found. Those can be opted into with
+/// [RetryPolicy::with_method]. Requests with a streamed body, like file
+/// uploads, are never retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts per request; 1 disables retrying
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_interval: Duration,
+    /// Upper bound for the delay between retries
+    pub max_interval: Duration,
+    /// Factor the delay grows by after each retry
+    pub multiplier: f64,
+    /// Methods that may be retried after a response or transport error
+    pub methods: Vec<Method>,
+    /// Response statuses that are worth retrying
+    pub statuses: Vec<StatusCode>,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Also retry requests with the given method
+    pub fn with_method(mut self, method: Method) -> Self {
+        if !self.methods.contains(&method) {
+            self.methods.push(method);
+        }
+        self
+    }
+
+    pub(crate) fn retry_status(&self, method: &Method, status: StatusCode) -> bool {
+        self.methods.contains(method) && self.statuses.contains(&status)
+    }
+
+    pub(crate) fn retry_error(&self, method: &Method, error: &reqwest::Error) -> bool {
+        error.is_connect() || (self.methods.contains(method) && error.is_timeout())
+    }
+
+    /// Delay before the given retry, starting at 1
+    pub(crate) fn interval(&self, retry: u32) -> Duration {
+        let exponent = i32::try_from(retry.saturating_sub(1)).unwrap_or(i32::MAX);
+        let secs = self.initial_interval.as_secs_f64() * self.multiplier.powi(exponent);
+        // Overflows, a NaN or a negative multiplier end up at the maximum
+        if !secs.is_finite() || secs < 0.0 {
+            return self.max_interval;
+        }
+        Duration::try_from_secs_f64(secs)
+            .unwrap_or(self.max_interval)
+            .min(self.max_interval)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_interval: Duration::from_millis(500),
+            max_interval: Duration::from_secs(10),
+            multiplier: 2.0,
+            methods: vec![Method::GET, Method::HEAD, Method::OPTIONS],
+            statuses: vec![
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_is_safe() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retry_status(&Method::GET, StatusCode::BAD_GATEWAY));
+        assert!(policy.retry_status(&Method::HEAD, StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!policy.retry_status(&Method::POST, StatusCode::BAD_GATEWAY));
+        assert!(!policy.retry_status(&Method::PUT, StatusCode::GATEWAY_TIMEOUT));
+        assert!(!policy.retry_status(&Method::DELETE, StatusCode::BAD_GATEWAY));
+        assert!(!policy.retry_status(&Method::GET, StatusCode::INTERNAL_SERVER_ERROR));
+
+        let policy = RetryPolicy::default().with_method(Method::PUT);
+        assert!(policy.retry_status(&Method::PUT, StatusCode::GATEWAY_TIMEOUT));
+        assert!(!policy.retry_status(&Method::DELETE, StatusCode::BAD_GATEWAY));
+    }
+
+    #[test]
+    fn interval() {
+        let policy = RetryPolicy::default();
+        assert_eq!(Duration::from_millis(500), policy.interval(1));
+        assert_eq!(Duration::from_secs(1), policy.interval(2));
+        assert_eq!(Duration::from_secs(10), policy.interval(10));
+        assert_eq!(Duration::from_secs(10), policy.interval(u32::MAX));
+
+        let policy = RetryPolicy {
+            max_interval: Duration::MAX,
+            ..Default::default()
+        };
+        assert_eq!(Duration::MAX, policy.interval(u32::MAX));
+
+        for multiplier in [f64::NAN, f64::INFINITY, -3.0] {
+            let policy = RetryPolicy {
+                multiplier,
+                ..Default::default()
+            };
+            assert_eq!(Duration::from_secs(10), policy.interval(2));
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use std::{collections::BTreeSet, fmt::Display, str::FromStr};
+
+/// A version of aptly as reported by `/api/version`, e.g. `1.5.0`, a
+/// pre-release like `1.6.0~rc1` or, for a build from git,
+/// `1.4.0+187+g15f2c97d`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AptlyVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+    original: String,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid aptly version '{0}'")]
+pub struct VersionParseError(String);
+
+impl AptlyVersion {
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            original: format!("{major}.{minor}.{patch}"),
+        }
+    }
+
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    pub fn patch(&self) -> u32 {
+        self.patch
+    }
+
+    /// Whether this is a build from git with commits on top of the release,
+    /// i.e. `git describe` output like `1.4.0+187+g15f2c97d`, which may
+    /// already have features of the next release
+    pub fn is_development(&self) -> bool {
+        let Some(describe) = self.suffix().strip_prefix('+') else {
+            return false;
+        };
+        let mut parts = describe.split('+');
+        let commits = parts.next().unwrap_or_default();
+        let hash = parts
+            .next()
+            .and_then(|h| h.strip_prefix('g'))
+            .unwrap_or_default();
+        !commits.is_empty()
+            && commits.bytes().all(|b| b.is_ascii_digit())
+            && !hash.is_empty()
+            && hash.bytes().all(|b| b.is_ascii_hexdigit())
+    }
+
+    /// Whether this is a pre-release like `1.6.0~rc1`, which sorts before
+    /// the release but is assumed to have its features
+    pub fn is_prerelease(&self) -> bool {
+        self.suffix().starts_with('~')
+    }
+
+    /// Everything after the release number e.g. `+ds1-1`
+    fn suffix(&self) -> &str {
+        let end = self
+            .original
+            .find(['+', '~', '-'])
+            .unwrap_or(self.original.len());
+        &self.original[end..]
+    }
+
+    fn release(&self) -> (u32, u32, u32) {
+        (self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for AptlyVersion {
+    type Err = VersionParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || VersionParseError(s.to_owned());
+        let release = s.trim().split(['+', '~', '-']).next().unwrap_or_default();
+        let mut parts = release.split('.').map(|p| p.parse::<u32>());
+
+        let major = parts.next().ok_or_else(invalid)?.map_err(|_| invalid())?;
+        let minor = parts.next().ok_or_else(invalid)?.map_err(|_| invalid())?;
+        let patch = parts
+            .next()
+            .transpose()
+            .map_err(|_| invalid())?
+            .unwrap_or_default();
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            original: s.trim().to_owned(),
+        })
+    }
+}
+
+impl Display for AptlyVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+impl PartialOrd for AptlyVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AptlyVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.release()
+            .cmp(&other.release())
+            .then_with(|| other.is_prerelease().cmp(&self.is_prerelease()))
+            .then_with(|| self.is_development().cmp(&other.is_development()))
+            .then_with(|| self.original.cmp(&other.original))
+    }
+}
+
+/// An API feature that not every aptly version has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Capability {
+    /// Background tasks: `_async` requests and `/api/tasks`
+    AsyncTasks,
+    /// Creating a snapshot by merging others with
+    /// `/api/snapshots/:name/merge`
+    SnapshotMerge,
+    /// Creating a snapshot by pulling packages from another one
+    SnapshotPull,
+    /// Publishing distributions into separate pool directories
+    MultiDist,
+    /// Importing keys with `/api/gpg/key`
+    GpgKeys,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] = [
+        Capability::AsyncTasks,
+        Capability::SnapshotMerge,
+        Capability::SnapshotPull,
+        Capability::MultiDist,
+        Capability::GpgKeys,
+    ];
+
+    /// The first release that has the feature
+    pub fn since(&self) -> AptlyVersion {
+        match self {
+            Capability::AsyncTasks | Capability::GpgKeys => AptlyVersion::new(1, 5, 0),
+            Capability::SnapshotMerge | Capability::SnapshotPull | Capability::MultiDist => {
+                AptlyVersion::new(1, 6, 0)
+            }
+        }
+    }
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Capability::AsyncTasks => "async tasks",
+            Capability::SnapshotMerge => "snapshot merge",
+            Capability::SnapshotPull => "snapshot pull",
+            Capability::MultiDist => "multi-dist publishing",
+            Capability::GpgKeys => "gpg key import",
+        })
+    }
+}
+
+/// The API features a server has, based on its version.
+///
+/// Development builds are assumed to have the features of the next minor
+/// release too, as they are typically deployed to get at those.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    version: AptlyVersion,
+    capabilities: BTreeSet<Capability>,
+}
+
+impl Capabilities {
+    pub fn for_version(version: AptlyVersion) -> Self {
+        let effective = if version.is_development() {
+            AptlyVersion::new(version.major, version.minor + 1, 0)
+        } else {
+            version.clone()
+        };
+        let capabilities = Capability::ALL
+            .into_iter()
+            .filter(|c| c.since().release() <= effective.release())
+            .collect();
+
+        Self {
+            version,
+            capabilities,
+        }
+    }
+
+    pub fn version(&self) -> &AptlyVersion {
+        &self.version
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Capability> + '_ {
+        self.capabilities.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let v: AptlyVersion = "1.4.0+187+g15f2c97d".parse().unwrap();
+        assert_eq!((1, 4, 0), v.release());
+        assert!(v.is_development());
+        assert_eq!("1.4.0+187+g15f2c97d", v.to_string());
+
+        let v: AptlyVersion = "1.6".parse().unwrap();
+        assert_eq!((1, 6, 0), v.release());
+        assert!(!v.is_development());
+
+        // A Debian package version is a plain release
+        let v: AptlyVersion = "1.5.0+ds1-1".parse().unwrap();
+        assert_eq!((1, 5, 0), v.release());
+        assert!(!v.is_development());
+        assert!(!v.is_prerelease());
+        assert!(!"1.5.0+ds1+gfoo"
+            .parse::<AptlyVersion>()
+            .unwrap()
+            .is_development());
+
+        let v: AptlyVersion = "1.6.0~rc1".parse().unwrap();
+        assert_eq!((1, 6, 0), v.release());
+        assert!(v.is_prerelease());
+        assert!(!v.is_development());
+
+        assert!("".parse::<AptlyVersion>().is_err());
+        assert!("1".parse::<AptlyVersion>().is_err());
+        assert!("1.x.0".parse::<AptlyVersion>().is_err());
+        assert!("1.2.3.4".parse::<AptlyVersion>().is_err());
+    }
+
+    #[test]
+    fn order() {
+        let versions: Vec<AptlyVersion> = [
+            "1.4.0",
+            "1.4.0+187+g15f2c97d",
+            "1.5.0",
+            "1.6.0~rc1",
+            "1.6.0",
+            "1.10.0",
+        ]
+        .iter()
+        .map(|v| v.parse().unwrap())
+        .collect();
+        assert!(versions.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn capabilities() {
+        let caps = Capabilities::for_version("1.5.0".parse().unwrap());
+        assert!(caps.supports(Capability::AsyncTasks));
+        assert!(!caps.supports(Capability::SnapshotPull));
+
+        let caps = Capabilities::for_version("1.4.0".parse().unwrap());
+        assert_eq!(0, caps.iter().count());
+
+        let caps = Capabilities::for_version("1.4.0+187+g15f2c97d".parse().unwrap());
+        assert!(caps.supports(Capability::AsyncTasks));
+        assert!(!caps.supports(Capability::MultiDist));
+
+        let caps = Capabilities::for_version("1.5.0+ds1-1".parse().unwrap());
+        assert!(caps.supports(Capability::AsyncTasks));
+        assert!(!caps.supports(Capability::SnapshotPull));
+
+        let caps = Capabilities::for_version("1.6.0~rc1".parse().unwrap());
+        assert!(caps.supports(Capability::SnapshotPull));
+
+        let caps = Capabilities::for_version("1.6.1".parse().unwrap());
+        assert!(Capability::ALL.iter().all(|c| caps.supports(*c)));
+    }
+}
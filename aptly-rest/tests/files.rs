@@ -0,0 +1,91 @@
+use std::sync::{Arc, Mutex};
+
+use aptly_rest::{
+    api::files::{Checksums, UploadFiles, UploadProgress},
+    AptlyRest, AptlyRestError,
+};
+use aptly_rest_mock::AptlyRestMock;
+
+fn checksums() -> Checksums {
+    // Of "first"
+    Checksums {
+        size: Some(5),
+        md5: Some("8b04d5e3775d298e78455efc5ca404d5".to_owned()),
+        sha1: Some("e0996a37c13d44c3b06074939d43fa3759bd32c1".to_owned()),
+        sha256: Some("a7937b64b8caa58f03721bb6bacf5c78cb235febe0e70b1b84cd99541461a08e".to_owned()),
+    }
+}
+
+#[tokio::test]
+async fn upload_progress() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let reported = progress.clone();
+    aptly
+        .files()
+        .directory("upload".to_owned())
+        .upload_with_progress(
+            UploadFiles::new()
+                .verified_file("a.deb".to_owned(), &b"first"[..], checksums())
+                .file("b.deb".to_owned(), &b"second"[..]),
+            move |p: &UploadProgress| reported.lock().unwrap().push(p.clone()),
+        )
+        .await
+        .expect("upload failed");
+
+    let progress = progress.lock().unwrap();
+    let a = progress.iter().rfind(|p| p.filename == "a.deb").unwrap();
+    assert_eq!(5, a.file_bytes);
+    assert_eq!(Some(5), a.file_size);
+    assert_eq!(5, a.total_bytes);
+    // Not all sizes are known
+    assert_eq!(None, a.total_size);
+
+    let last = progress.last().unwrap();
+    assert_eq!("b.deb", last.filename);
+    assert_eq!(6, last.file_bytes);
+    assert_eq!(11, last.total_bytes);
+}
+
+#[tokio::test]
+async fn upload_checksum_mismatch() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+    let files = aptly.files();
+    let directory = files.directory("upload".to_owned());
+
+    directory
+        .upload(UploadFiles::new().verified_file("a.deb".to_owned(), &b"first"[..], checksums()))
+        .await
+        .expect("upload with matching checksums failed");
+
+    let err = directory
+        .upload(UploadFiles::new().verified_file("a.deb".to_owned(), &b"fIrst"[..], checksums()))
+        .await
+        .unwrap_err();
+    match err {
+        AptlyRestError::Checksum(e) => {
+            assert_eq!("a.deb", e.filename);
+            assert_eq!("md5", e.kind);
+        }
+        e => panic!("Unexpected error: {e}"),
+    }
+
+    let err = directory
+        .upload(UploadFiles::new().verified_file(
+            "a.deb".to_owned(),
+            &b"first!"[..],
+            Checksums {
+                size: Some(5),
+                ..Default::default()
+            },
+        ))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, AptlyRestError::Checksum(e) if e.kind == "size"),
+        "{err}"
+    );
+}
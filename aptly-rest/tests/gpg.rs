@@ -0,0 +1,74 @@
+use aptly_rest::{
+    api::gpg::{ImportKeyOptions, KeySource},
+    AptlyRest, AptlyRestError,
+};
+use aptly_rest_mock::AptlyRestMock;
+
+const ARMORED: &str =
+    "-----BEGIN PGP PUBLIC KEY BLOCK-----\n\nmDMEZ...\n-----END PGP PUBLIC KEY BLOCK-----\n";
+
+#[tokio::test]
+async fn import_keys() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    let output = aptly
+        .gpg()
+        .import_key(
+            &KeySource::Keyserver {
+                keyserver: Some("hkps://keyserver.ubuntu.com".to_owned()),
+                key_ids: vec!["6A4BD0B4".to_owned(), "DF6C8E6F".to_owned()],
+            },
+            &ImportKeyOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert!(output.contains("processed: 2"), "{output}");
+    assert_eq!(
+        vec!["6A4BD0B4".to_owned(), "DF6C8E6F".to_owned()],
+        mock.gpg_keys("trustedkeys.gpg")
+    );
+
+    aptly
+        .gpg()
+        .import_key(
+            &KeySource::Armored(ARMORED.to_owned()),
+            &ImportKeyOptions {
+                keyring: Some("signing.gpg".to_owned()),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(vec![ARMORED.to_owned()], mock.gpg_keys("signing.gpg"));
+}
+
+#[tokio::test]
+async fn import_keys_errors() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    let err = aptly
+        .gpg()
+        .import_key(
+            &KeySource::Keyserver {
+                keyserver: None,
+                key_ids: vec![],
+            },
+            &ImportKeyOptions::default(),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AptlyRestError::BadRequest { .. }), "{err}");
+
+    mock.set_version("1.4.0");
+    let aptly = AptlyRest::new(mock.url());
+    let err = aptly
+        .gpg()
+        .import_key(
+            &KeySource::Armored(ARMORED.to_owned()),
+            &ImportKeyOptions::default(),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AptlyRestError::Unsupported { .. }), "{err}");
+}
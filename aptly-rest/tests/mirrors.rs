@@ -0,0 +1,164 @@
+use aptly_rest::{
+    api::mirrors::{CreateOptions, DeleteOptions, EditOptions, SnapshotOptions, UpdateOptions},
+    AptlyRest, AptlyRestError,
+};
+use aptly_rest_mock::AptlyRestMock;
+
+async fn create(aptly: &AptlyRest) {
+    aptly
+        .create_mirror(
+            "bookworm-main",
+            &"http://deb.debian.org/debian/".parse().unwrap(),
+            &CreateOptions {
+                distribution: Some("bookworm".to_owned()),
+                components: vec!["main".to_owned()],
+                architectures: vec!["amd64".to_owned()],
+                download_sources: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn mirror_create() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    create(&aptly).await;
+    assert_eq!(1, mock.mirrors().len());
+
+    let mirror = aptly.mirror("bookworm-main").get().await.unwrap();
+    assert_eq!("bookworm-main", mirror.name());
+    assert_eq!("http://deb.debian.org/debian/", mirror.archive_root());
+    assert_eq!("bookworm", mirror.distribution());
+    assert_eq!(&["main"], mirror.components());
+    assert_eq!(&["amd64"], mirror.architectures());
+    assert!(mirror.download_sources());
+    assert!(!mirror.download_udebs());
+    assert_eq!(None, mirror.filter());
+    assert_eq!(None, mirror.last_download_date());
+
+    let mirrors = aptly.mirrors().await.unwrap();
+    assert_eq!(1, mirrors.len());
+    assert_eq!("bookworm-main", mirrors[0].name());
+}
+
+#[tokio::test]
+async fn mirror_edit() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+    create(&aptly).await;
+
+    let mirror = aptly
+        .mirror("bookworm-main")
+        .edit(&EditOptions {
+            components: Some(vec!["main".to_owned(), "contrib".to_owned()]),
+            filter: Some("Priority (required)".to_owned()),
+            filter_with_deps: Some(true),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(&["main", "contrib"], mirror.components());
+    assert_eq!(Some("Priority (required)"), mirror.filter());
+    assert!(mirror.filter_with_deps());
+    // Fields that weren't given are left alone
+    assert_eq!(&["amd64"], mirror.architectures());
+    assert!(mirror.download_sources());
+
+    let mirror = aptly
+        .mirror("bookworm-main")
+        .edit(&EditOptions {
+            name: Some("bookworm".to_owned()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!("bookworm", mirror.name());
+    let err = aptly.mirror("bookworm-main").get().await.unwrap_err();
+    assert!(matches!(err, AptlyRestError::NotFound { .. }), "{err}");
+}
+
+#[tokio::test]
+async fn mirror_update() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+    create(&aptly).await;
+
+    let mirror = aptly
+        .mirror("bookworm-main")
+        .update(&UpdateOptions {
+            ignore_signatures: true,
+            max_tries: Some(3),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert!(mirror.last_download_date().is_some());
+    assert!(!mirror.is_updating());
+
+    let err = aptly
+        .mirror("missing")
+        .update(&UpdateOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AptlyRestError::NotFound { .. }), "{err}");
+}
+
+#[tokio::test]
+async fn mirror_snapshot() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+    create(&aptly).await;
+
+    let mirror = aptly.mirror("bookworm-main");
+    let snapshot = mirror
+        .snapshot("bookworm-1", &SnapshotOptions::default())
+        .await
+        .unwrap();
+    assert_eq!("bookworm-1", snapshot.name());
+    assert_eq!(
+        Some("Snapshot from mirror bookworm-main"),
+        snapshot.description()
+    );
+
+    let snapshot = mirror
+        .snapshot(
+            "bookworm-2",
+            &SnapshotOptions {
+                description: Some("Point release".to_owned()),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(Some("Point release"), snapshot.description());
+    assert_eq!(2, mock.mirror_snapshots().len());
+}
+
+#[tokio::test]
+async fn mirror_delete() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+    create(&aptly).await;
+
+    aptly
+        .mirror("bookworm-main")
+        .delete(&DeleteOptions::default())
+        .await
+        .unwrap();
+    assert!(mock.mirrors().is_empty());
+
+    let err = aptly
+        .mirror("bookworm-main")
+        .delete(&DeleteOptions { force: true })
+        .await
+        .unwrap_err();
+    match err {
+        AptlyRestError::NotFound { message } => {
+            assert_eq!("mirror with name bookworm-main not found", message)
+        }
+        e => panic!("Unexpected error: {e}"),
+    }
+}
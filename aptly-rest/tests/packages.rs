@@ -0,0 +1,28 @@
+use aptly_rest::{key::AptlyKey, AptlyRest, AptlyRestError};
+use aptly_rest_mock::AptlyRestMock;
+
+#[tokio::test]
+async fn get_package() {
+    let mock = AptlyRestMock::start().await;
+    mock.load_default_data();
+    let aptly = AptlyRest::new(mock.url());
+
+    let repo = mock.repos().get("bullseye-repo").unwrap().clone();
+    for key in repo.packages().iter().take(10) {
+        let key: AptlyKey = key.parse().unwrap();
+        let package = aptly.packages().get(&key).await.unwrap();
+        assert_eq!(&key, package.key());
+        assert_eq!(key.package(), package.package());
+        assert_eq!(key.is_source(), package.is_source());
+    }
+}
+
+#[tokio::test]
+async fn get_missing_package() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    let key: AptlyKey = "Pamd64 missing 1.0 0123456789abcdef".parse().unwrap();
+    let err = aptly.packages().get(&key).await.unwrap_err();
+    assert!(matches!(err, AptlyRestError::NotFound { .. }), "{err}");
+}
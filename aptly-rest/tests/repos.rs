@@ -1,7 +1,8 @@
 use std::str::FromStr;
 
-use aptly_rest::{key::AptlyKey, AptlyRest};
+use aptly_rest::{api::repos::PackageSelection, key::AptlyKey, AptlyRest, AptlyRestError};
 use aptly_rest_mock::AptlyRestMock;
+use futures::TryStreamExt;
 
 fn none_if_empty(v: &str) -> Option<&str> {
     if v.is_empty() {
@@ -73,3 +74,90 @@ async fn repo_packages_detailed() {
         assert!(repo_packages.contains(&key_s));
     }
 }
+
+#[tokio::test]
+async fn repo_packages_detailed_stream() {
+    let mock = AptlyRestMock::start().await;
+    mock.load_default_data();
+
+    let repos = mock.repos();
+    let repo = repos.get("bullseye-repo").unwrap();
+    let repo_packages = repo.packages();
+
+    let aptly = AptlyRest::new(mock.url());
+    let packages: Vec<_> = aptly
+        .repo("bullseye-repo")
+        .packages()
+        .detailed_stream()
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(repo_packages.len(), packages.len());
+    for p in packages {
+        let key_s = p.key().to_string();
+        assert!(repo_packages.contains(&key_s));
+    }
+
+    let err = aptly
+        .repo("missing-repo")
+        .packages()
+        .list_stream()
+        .try_next()
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AptlyRestError::NotFound { .. }));
+}
+
+#[tokio::test]
+async fn repo_packages_not_found() {
+    let mock = AptlyRestMock::start().await;
+    mock.load_default_data();
+
+    let aptly = AptlyRest::new(mock.url());
+    let err = aptly
+        .repo("missing-repo")
+        .packages()
+        .list()
+        .await
+        .unwrap_err();
+
+    match err {
+        AptlyRestError::NotFound { message } => {
+            assert_eq!("local repo with name missing-repo not found", message)
+        }
+        e => panic!("Unexpected error: {e}"),
+    }
+}
+
+#[tokio::test]
+async fn repo_select_keys() {
+    let mock = AptlyRestMock::start().await;
+    mock.load_default_data();
+
+    let aptly = AptlyRest::new(mock.url());
+    let repo = aptly.repo("bullseye-repo");
+    let packages = repo.packages();
+    let keys = packages.list().await.unwrap();
+    let selected = packages
+        .select(&PackageSelection::Keys(keys[..1].to_vec()))
+        .await
+        .unwrap();
+    assert_eq!(keys[..1], selected[..]);
+
+    let foreign = AptlyKey::from_str("Pamd64 not-in-repo 1.0 0123456789abcdef").unwrap();
+    let err = repo
+        .copy_packages(
+            "other-repo",
+            &PackageSelection::Keys(vec![keys[0].clone(), foreign.clone()]),
+        )
+        .await
+        .unwrap_err();
+    match err {
+        AptlyRestError::PackagesNotInRepo { repo, keys } => {
+            assert_eq!("bullseye-repo", repo);
+            assert_eq!(vec![foreign], keys);
+        }
+        e => panic!("Unexpected error: {e}"),
+    }
+}
@@ -0,0 +1,70 @@
+use std::time::Duration;
+
+use aptly_rest::{api::repos::EditOptions, retry::RetryPolicy, AptlyRest, AptlyRestError};
+use aptly_rest_mock::AptlyRestMock;
+use reqwest::{Method, StatusCode};
+
+fn aptly(mock: &AptlyRestMock, policy: RetryPolicy) -> AptlyRest {
+    AptlyRest::builder(mock.url())
+        .retry_policy(RetryPolicy {
+            initial_interval: Duration::from_millis(1),
+            ..policy
+        })
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn retry_unavailable() {
+    let mock = AptlyRestMock::start().await;
+    mock.load_default_data();
+    let aptly = aptly(&mock, RetryPolicy::default());
+
+    mock.fail_requests("GET", "/api/repos", 503, 1).await;
+    let repos = aptly.repos().await.unwrap();
+    assert!(!repos.is_empty());
+    assert_eq!(2, mock.received_requests("GET", "/api/repos").await);
+}
+
+#[tokio::test]
+async fn retry_gives_up() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = aptly(&mock, RetryPolicy::default());
+
+    mock.fail_requests("GET", "/api/repos", 503, 5).await;
+    let err = aptly.repos().await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            AptlyRestError::Server {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            }
+        ),
+        "{err}"
+    );
+    assert_eq!(3, mock.received_requests("GET", "/api/repos").await);
+}
+
+#[tokio::test]
+async fn no_retry_put_by_default() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = aptly(&mock, RetryPolicy::default());
+
+    mock.fail_requests("PUT", "/api/repos/test", 504, 5).await;
+    let err = aptly
+        .repo("test")
+        .edit(&EditOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AptlyRestError::Server { .. }), "{err}");
+    assert_eq!(1, mock.received_requests("PUT", "/api/repos/test").await);
+
+    let aptly = self::aptly(&mock, RetryPolicy::default().with_method(Method::PUT));
+    aptly
+        .repo("test")
+        .edit(&EditOptions::default())
+        .await
+        .unwrap_err();
+    assert_eq!(4, mock.received_requests("PUT", "/api/repos/test").await);
+}
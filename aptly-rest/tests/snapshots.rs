@@ -0,0 +1,128 @@
+use aptly_rest::{
+    api::snapshots::{CreateOptions, FilterOptions},
+    key::AptlyKey,
+    AptlyRest, AptlyRestError,
+};
+use aptly_rest_mock::AptlyRestMock;
+use serde_json::json;
+
+fn repo_keys(mock: &AptlyRestMock, repo: &str) -> Vec<AptlyKey> {
+    let repos = mock.repos();
+    repos.get(repo).unwrap().packages()[..2]
+        .iter()
+        .map(|k| k.parse().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn create_from_package_refs() {
+    let mock = AptlyRestMock::start().await;
+    mock.load_default_data();
+    let aptly = AptlyRest::new(mock.url());
+
+    let keys = repo_keys(&mock, "bullseye-repo");
+    let refs: Vec<String> = keys.iter().map(ToString::to_string).collect();
+
+    let snapshot = aptly
+        .create_snapshot(
+            "curated",
+            &keys,
+            &CreateOptions {
+                description: Some("Cherry-picked".to_owned()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!("curated", snapshot.name());
+    assert_eq!(Some("Cherry-picked"), snapshot.description());
+    assert_eq!(Some("2024-11-19T10:00:00Z"), snapshot.created_at());
+    assert_eq!(Some(refs.clone()), mock.snapshot_packages("curated"));
+
+    let snapshot = aptly
+        .create_snapshot("empty", [], &CreateOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(Some("Created as empty"), snapshot.description());
+
+    assert_eq!(
+        vec![
+            json!({"Name": "curated", "PackageRefs": refs, "Description": "Cherry-picked"}),
+            json!({"Name": "empty", "PackageRefs": []}),
+        ],
+        mock.received_bodies("POST", "/api/snapshots").await
+    );
+
+    let missing: AptlyKey = "Pamd64 missing 1.0 0123456789abcdef".parse().unwrap();
+    let err = aptly
+        .create_snapshot("broken", [&missing], &CreateOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AptlyRestError::NotFound { .. }), "{err}");
+    assert_eq!(None, mock.snapshot_packages("broken"));
+}
+
+#[tokio::test]
+async fn create_from_source_snapshots() {
+    let mock = AptlyRestMock::start().await;
+    mock.load_default_data();
+    let aptly = AptlyRest::new(mock.url());
+
+    let keys = repo_keys(&mock, "bullseye-repo");
+    let refs: Vec<String> = keys.iter().map(ToString::to_string).collect();
+    aptly
+        .create_snapshot("base", &keys[..1], &CreateOptions::default())
+        .await
+        .unwrap();
+
+    let options = CreateOptions {
+        source_snapshots: vec!["base".to_owned()],
+        ..Default::default()
+    };
+    let snapshot = aptly
+        .create_snapshot("frozen", &keys, &options)
+        .await
+        .unwrap();
+    assert_eq!("frozen", snapshot.name());
+    assert_eq!(Some("Merged from sources: 'base'"), snapshot.description());
+    assert_eq!(Some(refs.clone()), mock.snapshot_packages("frozen"));
+    assert_eq!(
+        Some(&json!({"Name": "frozen", "PackageRefs": refs, "SourceSnapshots": ["base"]})),
+        mock.received_bodies("POST", "/api/snapshots").await.last()
+    );
+    assert_eq!(2, mock.snapshots().len());
+
+    let options = CreateOptions {
+        source_snapshots: vec!["missing".to_owned()],
+        ..Default::default()
+    };
+    let err = aptly
+        .create_snapshot("orphan", &keys, &options)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, AptlyRestError::NotFound { message } if message.contains("missing")),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn filter_without_queries() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    let err = aptly
+        .snapshot("snap")
+        .filter("filtered", &[], &FilterOptions::default())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, AptlyRestError::EmptyFilter { snapshot } if snapshot == "snap"),
+        "{err}"
+    );
+    assert_eq!(
+        0,
+        mock.received_requests("GET", "/api/snapshots/snap/packages")
+            .await
+    );
+}
@@ -0,0 +1,46 @@
+use aptly_rest::AptlyRest;
+use aptly_rest_mock::AptlyRestMock;
+
+#[tokio::test]
+async fn health() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    assert!(aptly.healthy().await.unwrap());
+    assert!(aptly.ready().await.unwrap());
+
+    mock.set_ready(false);
+    assert!(!aptly.ready().await.unwrap());
+}
+
+#[tokio::test]
+async fn storage() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    let storage = aptly.storage().await.unwrap();
+    assert_eq!(102400, storage.total());
+    assert_eq!(25600, storage.free());
+    assert_eq!(75.0, storage.percent_full());
+}
+
+#[tokio::test]
+async fn metrics() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    let metrics = aptly.metrics().await.unwrap();
+    assert_eq!(3, metrics.families().len());
+    assert_eq!(
+        Some(3.0),
+        metrics.value("aptly_api_http_requests_total", &[("path", "/api/version")])
+    );
+}
+
+#[tokio::test]
+async fn db_recover() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    aptly.db_recover().await.unwrap();
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use aptly_rest::{api::tasks::TaskState, AptlyRest, AptlyRestError};
+use aptly_rest_mock::AptlyRestMock;
+
+#[tokio::test]
+async fn wait_finished() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    mock.set_task(1, "Publish snapshot", 2, "Done\n");
+    let task = aptly
+        .task(1)
+        .wait_timeout(Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(TaskState::Succeeded, task.state());
+    assert_eq!("Publish snapshot", task.name());
+}
+
+#[tokio::test]
+async fn wait_timeout() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    mock.set_task(2, "Update mirror", 1, "");
+    let err = aptly
+        .task(2)
+        .wait_with_interval(Duration::from_millis(10), Some(Duration::from_millis(100)))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, AptlyRestError::TaskTimeout { id: 2, .. }),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn result_failed() {
+    let mock = AptlyRestMock::start().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    mock.set_task(
+        3,
+        "Update published local repository",
+        3,
+        "ERROR: no space left\n",
+    );
+    let err = aptly.task(3).result::<()>().await.unwrap_err();
+    match err {
+        AptlyRestError::TaskFailed { id, output, .. } => {
+            assert_eq!(3, id);
+            assert_eq!("ERROR: no space left\n", output);
+        }
+        e => panic!("Unexpected error: {e}"),
+    }
+}
@@ -0,0 +1,38 @@
+#![cfg(unix)]
+
+use aptly_rest::{api::files::UploadFiles, AptlyRest};
+use aptly_rest_mock::AptlyRestMock;
+
+#[tokio::test]
+async fn repos_over_socket() {
+    let mock = AptlyRestMock::start_unix().await;
+    mock.load_default_data();
+    assert_eq!("unix", mock.url().scheme());
+
+    let aptly = AptlyRest::new(mock.url());
+    let received = aptly.repos().await.expect("failed to get repositories");
+    assert_eq!(mock.repos().len(), received.len());
+
+    let packages = aptly.repo("bullseye-repo").packages().list().await.unwrap();
+    assert_eq!(
+        mock.repos().get("bullseye-repo").unwrap().packages().len(),
+        packages.len()
+    );
+}
+
+#[tokio::test]
+async fn upload_over_socket() {
+    let mock = AptlyRestMock::start_unix().await;
+    let aptly = AptlyRest::new(mock.url());
+
+    aptly
+        .files()
+        .directory("upload".to_owned())
+        .upload(
+            UploadFiles::new()
+                .file("a.deb".to_owned(), &b"first"[..])
+                .file("b.deb".to_owned(), &b"second"[..]),
+        )
+        .await
+        .expect("upload failed");
+}
@@ -1,4 +1,12 @@
-use aptly_rest::AptlyRest;
+use aptly_rest::{
+    api::{
+        publish::{PublishOptions, SourceKind, UpdateOptions},
+        snapshots::{MergeOptions, PullOptions},
+    },
+    prefix::PublishPrefix,
+    version::{AptlyVersion, Capability},
+    AptlyRest, AptlyRestError,
+};
 use aptly_rest_mock::AptlyRestMock;
 
 #[tokio::test]
@@ -6,5 +14,119 @@ async fn test_version() {
     let mock = AptlyRestMock::start().await;
     let aptly = AptlyRest::new(mock.url());
     let version = aptly.version().await.unwrap();
-    assert_eq!(version, aptly_rest_mock::APTLY_VERSION);
+    assert_eq!(version.to_string(), aptly_rest_mock::APTLY_VERSION);
+}
+
+#[tokio::test]
+async fn version_is_cached() {
+    let mock = AptlyRestMock::start().await;
+    mock.set_version("1.5.0");
+    let aptly = AptlyRest::new(mock.url());
+    assert_eq!(AptlyVersion::new(1, 5, 0), aptly.version().await.unwrap());
+
+    mock.set_version("1.6.0");
+    assert_eq!(AptlyVersion::new(1, 5, 0), aptly.version().await.unwrap());
+    assert_eq!(
+        AptlyVersion::new(1, 6, 0),
+        AptlyRest::new(mock.url()).version().await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn unsupported_capability() {
+    let mock = AptlyRestMock::start().await;
+    mock.set_version("1.5.0");
+    let aptly = AptlyRest::new(mock.url());
+
+    let err = aptly
+        .snapshot("snap")
+        .pull("other", "pulled", &[], &PullOptions::default())
+        .await
+        .unwrap_err();
+    match err {
+        AptlyRestError::Unsupported {
+            capability,
+            version,
+        } => {
+            assert_eq!(Capability::SnapshotPull, capability);
+            assert_eq!(AptlyVersion::new(1, 5, 0), version);
+        }
+        e => panic!("Unexpected error: {e}"),
+    }
+}
+
+#[tokio::test]
+async fn supported_capability() {
+    let mock = AptlyRestMock::start().await;
+    mock.set_version("1.6.0");
+    let aptly = AptlyRest::new(mock.url());
+
+    // The mock doesn't implement pulling, so the request gets through to a 404
+    let err = aptly
+        .snapshot("snap")
+        .pull("other", "pulled", &[], &PullOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AptlyRestError::NotFound { .. }), "{err}");
+}
+
+#[tokio::test]
+async fn publish_multi_dist_unsupported() {
+    let mock = AptlyRestMock::start().await;
+    mock.set_version("1.5.0");
+    let aptly = AptlyRest::new(mock.url());
+
+    let options = PublishOptions {
+        multi_dist: true,
+        ..Default::default()
+    };
+    let err = aptly
+        .publish_prefix(PublishPrefix::new("apertis"))
+        .publish(SourceKind::Local, &[], &options)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            AptlyRestError::Unsupported {
+                capability: Capability::MultiDist,
+                ..
+            }
+        ),
+        "{err}"
+    );
+
+    let options = UpdateOptions {
+        multi_dist: Some(true),
+        ..Default::default()
+    };
+    let err = aptly
+        .publish_prefix(PublishPrefix::new("apertis"))
+        .distribution("v2024")
+        .update(&options)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AptlyRestError::Unsupported { .. }), "{err}");
+}
+
+#[tokio::test]
+async fn snapshot_merge_unsupported() {
+    let mock = AptlyRestMock::start().await;
+    mock.set_version("1.5.0");
+    let aptly = AptlyRest::new(mock.url());
+
+    let err = aptly
+        .merge_snapshots("merged", &["a".to_owned()], &MergeOptions::default())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            AptlyRestError::Unsupported {
+                capability: Capability::SnapshotMerge,
+                ..
+            }
+        ),
+        "{err}"
+    );
 }
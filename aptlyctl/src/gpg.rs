@@ -0,0 +1,63 @@
+use std::{io::Read, path::PathBuf, process::ExitCode};
+
+use aptly_rest::{
+    api::gpg::{ImportKeyOptions, KeySource},
+    AptlyRest,
+};
+use clap::{Parser, Subcommand};
+use color_eyre::Result;
+
+#[derive(Parser, Debug)]
+pub struct GpgImportOpts {
+    /// Ids of the keys to fetch from the keyserver
+    #[clap(required_unless_present = "armored")]
+    key_ids: Vec<String>,
+    /// Keyserver to fetch the keys from instead of aptly's default
+    #[clap(long, conflicts_with = "armored")]
+    keyserver: Option<String>,
+    /// Import the ASCII armored keys in this file, or stdin for `-`
+    #[clap(long, conflicts_with = "key_ids")]
+    armored: Option<PathBuf>,
+    /// Keyring on the server to add the keys to
+    #[clap(long)]
+    keyring: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum GpgCommand {
+    Import(GpgImportOpts),
+}
+
+impl GpgCommand {
+    pub async fn run(self, aptly: &AptlyRest) -> Result<ExitCode> {
+        match self {
+            GpgCommand::Import(args) => {
+                let source = match args.armored {
+                    Some(path) if path.as_os_str() == "-" => {
+                        let mut armor = String::new();
+                        std::io::stdin().read_to_string(&mut armor)?;
+                        KeySource::Armored(armor)
+                    }
+                    Some(path) => KeySource::Armored(tokio::fs::read_to_string(path).await?),
+                    None => KeySource::Keyserver {
+                        keyserver: args.keyserver,
+                        key_ids: args.key_ids,
+                    },
+                };
+
+                let output = aptly
+                    .gpg()
+                    .import_key(
+                        &source,
+                        &ImportKeyOptions {
+                            keyring: args.keyring,
+                        },
+                    )
+                    .await?;
+                print!("{output}");
+            }
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
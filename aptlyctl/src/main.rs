@@ -1,19 +1,27 @@
 use std::process::ExitCode;
 
-use aptly_rest::AptlyRest;
+use aptly_rest::args::AptlyArgs;
 use clap::{Parser, Subcommand, ValueEnum};
 use color_eyre::Result;
+use gpg::GpgCommand;
+use package::PackageCommand;
 use publish::PublishCommand;
 use repo::RepoCommand;
 use snapshot::SnapshotCommand;
+use status::StatusOpts;
+use task::TaskCommand;
 use tools::ToolsCommand;
 use tracing::{info, metadata::LevelFilter};
 use tracing_error::ErrorLayer;
 use tracing_subscriber::prelude::*;
 
+mod gpg;
+mod package;
 mod publish;
 mod repo;
 mod snapshot;
+mod status;
+mod task;
 mod tools;
 
 #[derive(ValueEnum, Clone, Copy, Debug, Default)]
@@ -33,32 +41,38 @@ enum Command {
         #[clap(subcommand)]
         command: PublishCommand,
     },
+    Package {
+        #[clap(subcommand)]
+        command: PackageCommand,
+    },
     Snapshot {
         #[clap(subcommand)]
         command: SnapshotCommand,
     },
+    Task {
+        #[clap(subcommand)]
+        command: TaskCommand,
+    },
     Tools {
         #[clap(subcommand)]
         command: ToolsCommand,
     },
+    Gpg {
+        #[clap(subcommand)]
+        command: GpgCommand,
+    },
+    /// Show the version, health and disk usage of the server
+    Status(StatusOpts),
     DbCleanup,
+    DbRecover,
 }
 
 #[derive(Parser, Debug)]
 struct Opts {
     #[clap(subcommand)]
     command: Command,
-    /// Url for the aptly rest API endpoint
-    #[clap(
-        short = 'u',
-        long,
-        env = "APTLY_API_URL",
-        default_value = "http://localhost:8080"
-    )]
-    api_url: url::Url,
-    /// Authentication token for the API
-    #[clap(long, env = "APTLY_API_TOKEN")]
-    api_token: Option<String>,
+    #[clap(flatten)]
+    aptly: AptlyArgs,
 }
 
 #[tokio::main]
@@ -69,21 +83,26 @@ async fn main() -> Result<ExitCode> {
         .init();
     color_eyre::install().unwrap();
     let opts = Opts::parse();
-    let aptly = if let Some(token) = opts.api_token {
-        AptlyRest::new_with_token(opts.api_url, &token)?
-    } else {
-        AptlyRest::new(opts.api_url)
-    };
+    let aptly = opts.aptly.build()?;
 
     match opts.command {
         Command::Repo { command } => command.run(&aptly).await,
         Command::Publish { command } => command.run(&aptly).await,
+        Command::Package { command } => command.run(&aptly).await,
         Command::Snapshot { command } => command.run(&aptly).await,
+        Command::Task { command } => command.run(&aptly).await,
         Command::Tools { command } => command.run().await,
+        Command::Gpg { command } => command.run(&aptly).await,
+        Command::Status(args) => args.run(&aptly).await,
         Command::DbCleanup => {
             aptly.db_cleanup().await?;
             info!("Ran database cleanup");
             Ok(ExitCode::SUCCESS)
         }
+        Command::DbRecover => {
+            aptly.db_recover().await?;
+            info!("Ran database recovery");
+            Ok(ExitCode::SUCCESS)
+        }
     }
 }
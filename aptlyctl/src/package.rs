@@ -0,0 +1,99 @@
+use std::{io::stdout, path::PathBuf, process::ExitCode};
+
+use aptly_rest::{dsc::Dsc, key::AptlyKey, query::Query, AptlyRest, AptlyRestError};
+use clap::{Parser, Subcommand};
+use color_eyre::{eyre::eyre, Result};
+
+use crate::OutputFormat;
+
+#[derive(Clone, Debug)]
+enum PackageRef {
+    Key(AptlyKey),
+    Dsc(PathBuf),
+}
+
+impl PackageRef {
+    async fn key(self) -> Result<AptlyKey> {
+        match self {
+            PackageRef::Key(key) => Ok(key),
+            PackageRef::Dsc(path) => {
+                let dsc = Dsc::from_file(path).await?;
+                Ok(AptlyKey::try_from(&dsc)?)
+            }
+        }
+    }
+}
+
+fn parse_package_ref(s: &str) -> Result<PackageRef, Box<dyn std::error::Error + Send + Sync>> {
+    if s.ends_with(".dsc") {
+        Ok(PackageRef::Dsc(s.into()))
+    } else {
+        Ok(PackageRef::Key(s.parse()?))
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct PackageShowOpts {
+    /// Aptly key of the package, or a .dsc file to compute the key of
+    #[clap(value_parser = parse_package_ref)]
+    package: PackageRef,
+    #[clap(long, value_enum, default_value_t)]
+    format: OutputFormat,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PackageCommand {
+    /// Show a package in the pool and the repos that contain it
+    Show(PackageShowOpts),
+}
+
+/// Names of the local repos that contain the package
+async fn repos_containing(aptly: &AptlyRest, key: &AptlyKey) -> Result<Vec<String>> {
+    let query = Query::reference(key.package(), key.version().to_string(), key.arch());
+    let mut names = vec![];
+    for repo in aptly.repos().await? {
+        let keys = aptly
+            .repo(repo.name())
+            .packages()
+            .query(&query, false)
+            .list()
+            .await?;
+        if keys.contains(key) {
+            names.push(repo.name().to_owned());
+        }
+    }
+    Ok(names)
+}
+
+impl PackageCommand {
+    pub async fn run(self, aptly: &AptlyRest) -> Result<ExitCode> {
+        match self {
+            PackageCommand::Show(args) => {
+                let key = args.package.key().await?;
+                let package = match aptly.packages().get(&key).await {
+                    Ok(package) => package,
+                    Err(AptlyRestError::NotFound { .. }) => {
+                        return Err(eyre!("Package {key} not found in the pool"))
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+                let repos = repos_containing(aptly, &key).await?;
+
+                match args.format {
+                    OutputFormat::Name => {
+                        print!("{}", package.control_paragraph());
+                        println!("Repos: {}", repos.join(", "));
+                    }
+                    OutputFormat::Json => {
+                        let mut json = serde_json::to_value(&package)?;
+                        json["Repos"] = repos.into();
+                        serde_json::to_writer_pretty(&mut stdout(), &json)?;
+                        println!();
+                    }
+                }
+            }
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
@@ -1,6 +1,6 @@
 use std::{io::stdout, process::ExitCode};
 
-use aptly_rest::{api::publish, AptlyRest};
+use aptly_rest::{api::publish, prefix::PublishPrefix, AptlyRest};
 use clap::{Parser, Subcommand, ValueEnum};
 use color_eyre::Result;
 use tracing::{debug, info};
@@ -36,22 +36,60 @@ fn parse_source(s: &str) -> Result<publish::Source, Box<dyn std::error::Error +
     }
 }
 
+fn parse_switch_source(
+    s: &str,
+) -> Result<publish::Source, Box<dyn std::error::Error + Send + Sync>> {
+    if let Some((component, name)) = s.split_once('=') {
+        Ok(publish::Source {
+            name: name.to_owned(),
+            component: Some(component.to_owned()),
+        })
+    } else {
+        Ok(publish::Source {
+            name: s.to_owned(),
+            component: None,
+        })
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct PublishCreateOpts {
     kind: SourceKind,
-    prefix: String,
+    prefix: PublishPrefix,
     #[clap(value_parser = parse_source)]
     sources: Vec<publish::Source>,
     #[clap(long = "architecture")]
     architectures: Vec<String>,
     #[clap(long)]
     distribution: Option<String>,
+    /// Suite of the Release file, if different from the distribution
+    #[clap(long)]
+    suite: Option<String>,
+    /// Codename of the Release file, if different from the distribution
+    #[clap(long)]
+    codename: Option<String>,
+    #[clap(long)]
+    label: Option<String>,
+    #[clap(long)]
+    origin: Option<String>,
     #[clap(long)]
     gpg_key: Option<String>,
     #[clap(long)]
     skip_bz2: bool,
     #[clap(long)]
     skip_contents: bool,
+    #[clap(long)]
+    acquire_by_hash: bool,
+    #[clap(long)]
+    not_automatic: bool,
+    #[clap(long)]
+    but_automatic_upgrades: bool,
+    /// Give the distribution a pool directory of its own (aptly 1.6+)
+    #[clap(long)]
+    multi_dist: bool,
+    /// Run the publish as an aptly background task
+    #[clap(long = "async")]
+    use_task: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -62,14 +100,40 @@ pub struct PublishListOpts {
 
 #[derive(Parser, Debug)]
 pub struct PublishTestExistsOpts {
-    prefix: String,
+    prefix: PublishPrefix,
     distribution: String,
 }
 
 #[derive(Parser, Debug)]
 pub struct PublishUpdateOpts {
-    prefix: String,
+    prefix: PublishPrefix,
+    distribution: String,
+    #[clap(long)]
+    gpg_key: Option<String>,
+    #[clap(long)]
+    skip_bz2: bool,
+    #[clap(long)]
+    skip_contents: bool,
+    /// Change whether upgrades are automatic for a NotAutomatic distribution
+    #[clap(long)]
+    but_automatic_upgrades: Option<bool>,
+    /// Change whether the distribution has a pool directory of its own
+    /// (aptly 1.6+)
+    #[clap(long)]
+    multi_dist: Option<bool>,
+    /// Run the update as an aptly background task
+    #[clap(long = "async")]
+    use_task: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct PublishSwitchOpts {
+    prefix: PublishPrefix,
     distribution: String,
+    /// Snapshots to switch to as component=snapshot; the component may be
+    /// left out if the publish only has one
+    #[clap(value_parser = parse_switch_source, required = true)]
+    snapshots: Vec<publish::Source>,
     #[clap(long)]
     gpg_key: Option<String>,
     #[clap(long)]
@@ -80,7 +144,7 @@ pub struct PublishUpdateOpts {
 
 #[derive(Parser, Debug)]
 pub struct PublishDropOpts {
-    prefix: String,
+    prefix: PublishPrefix,
     distribution: String,
     #[clap(long)]
     force: bool,
@@ -94,6 +158,7 @@ pub enum PublishCommand {
     List(PublishListOpts),
     TestExists(PublishTestExistsOpts),
     Update(PublishUpdateOpts),
+    Switch(PublishSwitchOpts),
     Drop(PublishDropOpts),
 }
 
@@ -110,23 +175,40 @@ impl PublishCommand {
                     publish::Signing::Disabled
                 };
 
-                let repo = aptly
-                    .publish_prefix(&args.prefix)
-                    .publish(
-                        args.kind.into(),
-                        &args.sources,
-                        &publish::PublishOptions {
-                            architectures: args.architectures,
-                            distribution: args.distribution,
-                            signing: Some(signing),
-                            skip_bz2: args.skip_bz2,
-                            skip_contents: args.skip_contents,
-                            ..Default::default()
-                        },
-                    )
-                    .await?;
+                let options = publish::PublishOptions {
+                    architectures: args.architectures,
+                    distribution: args.distribution,
+                    suite: args.suite,
+                    codename: args.codename,
+                    label: args.label,
+                    origin: args.origin,
+                    signing: Some(signing),
+                    skip_bz2: args.skip_bz2,
+                    skip_contents: args.skip_contents,
+                    acquire_by_hash: args.acquire_by_hash,
+                    not_automatic: args.not_automatic,
+                    but_automatic_upgrades: args.but_automatic_upgrades,
+                    multi_dist: args.multi_dist,
+                    ..Default::default()
+                };
+
+                let publish_api = aptly.publish_prefix(&args.prefix);
+                let repo: publish::PublishedRepo = if args.use_task {
+                    let task = publish_api
+                        .publish_async(args.kind.into(), &args.sources, &options)
+                        .await?;
+                    info!("Started publish task {}", task.id());
+                    task.result().await?
+                } else {
+                    publish_api
+                        .publish(args.kind.into(), &args.sources, &options)
+                        .await?
+                };
                 debug!(?repo);
-                info!("Created new published repository at '{}'", repo.prefix());
+                info!(
+                    "Created new published repository at '{}'",
+                    repo.publish_prefix()
+                );
             }
             PublishCommand::List(args) => {
                 let publishes = aptly.published().await?;
@@ -135,7 +217,7 @@ impl PublishCommand {
                     OutputFormat::Name => {
                         let mut names: Vec<_> = publishes
                             .iter()
-                            .map(|p| format!("{} {}", p.prefix(), p.distribution()))
+                            .map(|p| format!("{} {}", p.publish_prefix(), p.distribution()))
                             .collect();
                         names.sort();
                         for name in names {
@@ -150,10 +232,9 @@ impl PublishCommand {
             }
             PublishCommand::TestExists(args) => {
                 let publishes = aptly.published().await?;
-                if !publishes
-                    .iter()
-                    .any(|p| p.prefix() == args.prefix && p.distribution() == args.distribution)
-                {
+                if !publishes.iter().any(|p| {
+                    p.publish_prefix() == args.prefix && p.distribution() == args.distribution
+                }) {
                     return Ok(ExitCode::FAILURE);
                 }
             }
@@ -167,30 +248,72 @@ impl PublishCommand {
                     publish::Signing::Disabled
                 };
 
+                let options = publish::UpdateOptions {
+                    skip_bz2: args.skip_bz2,
+                    skip_contents: args.skip_contents,
+                    signing: Some(signing),
+                    but_automatic_upgrades: args.but_automatic_upgrades,
+                    multi_dist: args.multi_dist,
+                    ..Default::default()
+                };
+
+                let publish_api = aptly.publish_prefix(&args.prefix);
+                let distribution = publish_api.distribution(&args.distribution);
+                let repo: publish::PublishedRepo = if args.use_task {
+                    let task = distribution.update_async(&options).await?;
+                    info!("Started update task {}", task.id());
+                    task.result().await?
+                } else {
+                    distribution.update(&options).await?
+                };
+                debug!(?repo);
+                info!(
+                    "Updated published repository at '{}/{}'",
+                    repo.publish_prefix(),
+                    repo.distribution()
+                );
+            }
+            PublishCommand::Switch(args) => {
+                let signing = if let Some(key) = args.gpg_key {
+                    publish::Signing::Enabled(publish::SigningOptions {
+                        gpg_key: Some(key),
+                        ..Default::default()
+                    })
+                } else {
+                    publish::Signing::Disabled
+                };
+
+                let options = publish::UpdateOptions {
+                    skip_bz2: args.skip_bz2,
+                    skip_contents: args.skip_contents,
+                    signing: Some(signing),
+                    ..Default::default()
+                };
+
                 let repo = aptly
                     .publish_prefix(&args.prefix)
                     .distribution(&args.distribution)
-                    .update(&publish::UpdateOptions {
-                        skip_bz2: args.skip_bz2,
-                        skip_contents: args.skip_contents,
-                        signing: Some(signing),
-                        ..Default::default()
-                    })
+                    .switch(&args.snapshots, &options)
                     .await?;
                 debug!(?repo);
+                for source in repo.sources() {
+                    info!(
+                        "{} now publishes '{}'",
+                        source.component.as_deref().unwrap_or_default(),
+                        source.name
+                    );
+                }
                 info!(
-                    "Updated published repository at '{}/{}'",
-                    repo.prefix(),
+                    "Switched published repository at '{}/{}'",
+                    repo.publish_prefix(),
                     repo.distribution()
                 );
             }
             PublishCommand::Drop(args) => {
                 if args.ignore_if_missing
-                    && !aptly
-                        .published()
-                        .await?
-                        .into_iter()
-                        .any(|p| p.prefix() == args.prefix && p.distribution() == args.distribution)
+                    && !aptly.published().await?.into_iter().any(|p| {
+                        p.publish_prefix() == args.prefix && p.distribution() == args.distribution
+                    })
                 {
                     info!("Not published; doing nothing.");
                 } else {
@@ -1,10 +1,21 @@
-use std::{io::stdout, process::ExitCode};
+use std::{
+    io::stdout,
+    path::PathBuf,
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use aptly_rest::{api::repos, key::AptlyKey, AptlyRest, AptlyRestError};
+use aptly_rest::{
+    api::{files::UploadFiles, repos},
+    changes::Changes,
+    key::AptlyKey,
+    query::Query,
+    AptlyRest, AptlyRestError,
+};
 use clap::{Parser, Subcommand};
-use color_eyre::Result;
-use http::StatusCode;
-use tracing::{debug, info, warn};
+use color_eyre::{eyre::eyre, Result};
+use tokio::fs::File;
+use tracing::{debug, error, info, warn};
 
 use crate::OutputFormat;
 
@@ -12,7 +23,7 @@ use crate::OutputFormat;
 pub struct RepoPackagesListOpts {
     repo: String,
     #[clap(long, short, default_value("Name"))]
-    query: String,
+    query: Query,
     #[clap(long, short)]
     fail_if_empty: bool,
     #[clap(long, value_enum, default_value_t)]
@@ -25,7 +36,7 @@ pub struct RepoPackagesDeleteOpts {
     #[clap(long = "key", short, required_unless_present("queries"))]
     keys: Vec<AptlyKey>,
     #[clap(long = "query", short, required_unless_present("keys"))]
-    queries: Vec<String>,
+    queries: Vec<Query>,
     #[clap(long, short = 'n', default_value_t)]
     dry_run: bool,
 }
@@ -83,7 +94,7 @@ impl RepoPackagesCommand {
                     for key in &keys {
                         info!("{key}");
                     }
-                    args.keys.extend(keys.into_iter());
+                    args.keys.extend(keys);
                 }
 
                 if args.keys.is_empty() {
@@ -119,6 +130,20 @@ pub struct RepoCreateOpts {
     distribution: Option<String>,
 }
 
+#[derive(Parser, Debug)]
+pub struct RepoEditOpts {
+    repo: String,
+    /// New name for the repo
+    #[clap(long)]
+    name: Option<String>,
+    #[clap(long)]
+    comment: Option<String>,
+    #[clap(long)]
+    component: Option<String>,
+    #[clap(long)]
+    distribution: Option<String>,
+}
+
 #[derive(Parser, Debug)]
 pub struct RepoListOpts {
     #[clap(long, value_enum, default_value_t)]
@@ -133,7 +158,7 @@ pub struct RepoTestExistsOpts {
 #[derive(Parser, Debug)]
 pub struct RepoSearchOpts {
     repo: String,
-    query: String,
+    query: Query,
     #[clap(long, short)]
     exit_code: bool,
     #[clap(long, value_enum, default_value_t)]
@@ -146,6 +171,95 @@ pub struct RepoSnapshotOpts {
     snapshot: String,
 }
 
+#[derive(Parser, Debug)]
+pub struct RepoIncludeOpts {
+    repo: String,
+    changes: PathBuf,
+    #[clap(long)]
+    force_replace: bool,
+    /// Keep the uploaded files on the aptly server after including them
+    #[clap(long)]
+    no_remove_files: bool,
+    #[clap(long)]
+    accept_unsigned: bool,
+    #[clap(long)]
+    ignore_signature: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct RepoCopyOpts {
+    /// Repo to take the packages from
+    source: String,
+    /// Repo to add the packages to
+    target: String,
+    #[clap(long = "key", short, required_unless_present("queries"))]
+    keys: Vec<AptlyKey>,
+    #[clap(long = "query", short, required_unless_present("keys"))]
+    queries: Vec<Query>,
+    /// Also include the dependencies of packages matching the queries
+    #[clap(long)]
+    with_deps: bool,
+    #[clap(long, short = 'n', default_value_t)]
+    dry_run: bool,
+}
+
+impl RepoCopyOpts {
+    async fn run(self, aptly: &AptlyRest, remove_source: bool) -> Result<ExitCode> {
+        let action = if remove_source { "move" } else { "copy" };
+        let source = aptly.repo(&self.source);
+
+        let mut keys = self.keys;
+        for query in self.queries {
+            info!("Finding packages for query '{query}'...");
+            let found = source
+                .packages()
+                .select(&repos::PackageSelection::Query {
+                    query,
+                    with_deps: self.with_deps,
+                })
+                .await?;
+            info!("Query found {} package(s)", found.len());
+            keys.extend(found);
+        }
+        keys.sort();
+        keys.dedup();
+
+        if keys.is_empty() {
+            info!("No packages to {action}");
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        if self.dry_run {
+            info!(
+                "Would {action} {} package(s) from '{}' to '{}'",
+                keys.len(),
+                self.source,
+                self.target
+            );
+            for key in keys {
+                println!("{}", key);
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        let selection = repos::PackageSelection::Keys(keys);
+        let keys = if remove_source {
+            source.move_packages(&self.target, &selection).await?
+        } else {
+            source.copy_packages(&self.target, &selection).await?
+        };
+        info!(
+            "{} {} package(s) from '{}' to '{}'",
+            if remove_source { "Moved" } else { "Copied" },
+            keys.len(),
+            self.source,
+            self.target
+        );
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
 #[derive(Parser, Debug)]
 pub struct RepoCleanOpts {
     repo: String,
@@ -161,6 +275,7 @@ pub struct RepoDropOpts {
 #[derive(Subcommand, Debug)]
 pub enum RepoCommand {
     Create(RepoCreateOpts),
+    Edit(RepoEditOpts),
     List(RepoListOpts),
     #[clap(subcommand)]
     Packages(RepoPackagesCommand),
@@ -168,6 +283,9 @@ pub enum RepoCommand {
     #[clap(hide(true))]
     Search(RepoSearchOpts),
     Snapshot(RepoSnapshotOpts),
+    Include(RepoIncludeOpts),
+    Copy(RepoCopyOpts),
+    Move(RepoCopyOpts),
     Clean(RepoCleanOpts),
     Drop(RepoDropOpts),
 }
@@ -187,6 +305,20 @@ impl RepoCommand {
                 info!("Created repo '{}'", repo.name());
             }
 
+            RepoCommand::Edit(args) => {
+                let repo = aptly
+                    .repo(&args.repo)
+                    .edit(&repos::EditOptions {
+                        name: args.name,
+                        comment: args.comment,
+                        distribution: args.distribution,
+                        component: args.component,
+                    })
+                    .await?;
+                debug!(?repo);
+                info!("Updated repo '{}'", repo.name());
+            }
+
             RepoCommand::List(args) => {
                 let repos = aptly.repos().await?;
                 match args.format {
@@ -219,17 +351,11 @@ impl RepoCommand {
                 .await;
             }
 
-            RepoCommand::TestExists(args) => {
-                if let Err(err) = aptly.repo(&args.repo).get().await {
-                    if let AptlyRestError::Request(err) = &err {
-                        if err.status() == Some(StatusCode::NOT_FOUND) {
-                            return Ok(ExitCode::FAILURE);
-                        }
-                    }
-
-                    return Err(err.into());
-                }
-            }
+            RepoCommand::TestExists(args) => match aptly.repo(&args.repo).get().await {
+                Ok(_) => (),
+                Err(AptlyRestError::NotFound { .. }) => return Ok(ExitCode::FAILURE),
+                Err(err) => return Err(err.into()),
+            },
 
             RepoCommand::Snapshot(args) => {
                 let snapshot = aptly
@@ -243,6 +369,99 @@ impl RepoCommand {
                 );
             }
 
+            RepoCommand::Include(args) => {
+                let changes = Changes::from_file(args.changes.clone()).await?;
+                let changes_name = args
+                    .changes
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .ok_or_else(|| eyre!("Invalid changes file name"))?
+                    .to_owned();
+                let base = args.changes.parent().unwrap_or(&args.changes);
+
+                // Unique per invocation, so concurrent includes of the same
+                // source don't overwrite each other's files
+                let nonce = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos();
+                let upload_dir = format!(
+                    "aptlyctl-include-{}-{}-{:x}",
+                    changes.source()?,
+                    std::process::id(),
+                    nonce
+                );
+                let files = aptly.files();
+                let directory = files.directory(upload_dir.clone());
+
+                let result = async {
+                    let mut upload = UploadFiles::new();
+                    for file in changes.files()? {
+                        upload.add_verified_file(
+                            file.name.clone(),
+                            File::open(base.join(&file.name)).await?,
+                            (&file).into(),
+                        );
+                    }
+                    upload.add_file(changes_name.clone(), File::open(&args.changes).await?);
+                    directory
+                        .upload_with_progress(upload, |progress| {
+                            if progress.file_size == Some(progress.file_bytes) {
+                                info!("Uploaded {}", progress.filename);
+                            }
+                        })
+                        .await?;
+
+                    info!("Including {changes_name} into repo '{}'...", args.repo);
+                    let response = aptly
+                        .repo(&args.repo)
+                        .files()
+                        .include_file(
+                            &upload_dir,
+                            &changes_name,
+                            &repos::IncludeOptions {
+                                force_replace: args.force_replace,
+                                no_remove_files: args.no_remove_files,
+                                accept_unsigned: args.accept_unsigned,
+                                ignore_signature: args.ignore_signature,
+                            },
+                        )
+                        .await?;
+                    Ok::<_, color_eyre::Report>(response)
+                }
+                .await;
+
+                if !args.no_remove_files {
+                    match directory.delete().await {
+                        Ok(()) | Err(AptlyRestError::NotFound { .. }) => (),
+                        // Don't hide why the include failed
+                        Err(err) if result.is_err() => {
+                            warn!("Failed to remove upload directory {upload_dir}: {err}")
+                        }
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                let response = result?;
+                debug!(?response);
+
+                for warning in response.report().warnings() {
+                    warn!("{warning}");
+                }
+                for added in response.report().added() {
+                    info!("{added}");
+                }
+
+                if !response.failed_files().is_empty() {
+                    for file in response.failed_files() {
+                        error!("Failed to include {file}");
+                    }
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+
+            RepoCommand::Copy(args) => return args.run(aptly, false).await,
+            RepoCommand::Move(args) => return args.run(aptly, true).await,
+
             RepoCommand::Clean(args) => {
                 warn!("'aptlyctl repo clean <REPO>' is deprecated");
                 warn!("Use 'aptlyctl repo packages delete -q Name <REPO>' instead");
@@ -250,7 +469,7 @@ impl RepoCommand {
                 return RepoPackagesCommand::Delete(RepoPackagesDeleteOpts {
                     repo: args.repo,
                     keys: vec![],
-                    queries: vec!["Name".to_owned()],
+                    queries: vec![Query::all()],
                     dry_run: false,
                 })
                 .run(aptly)
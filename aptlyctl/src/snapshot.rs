@@ -1,13 +1,87 @@
-use std::{io::stdout, process::ExitCode};
+use std::{cmp::Ordering, io::stdout, process::ExitCode};
 
-use aptly_rest::{AptlyRest, AptlyRestError};
+use aptly_rest::{
+    api::snapshots::{self, SnapshotDiff},
+    key::AptlyKey,
+    query::Query,
+    AptlyRest, AptlyRestError,
+};
 use clap::{Parser, Subcommand};
 use color_eyre::Result;
-use http::StatusCode;
+use serde_json::json;
 use tracing::info;
 
 use crate::OutputFormat;
 
+#[derive(Parser, Debug, Clone)]
+pub struct SnapshotPackagesListOpts {
+    snapshot: String,
+    #[clap(long, short, default_value("Name"))]
+    query: Query,
+    #[clap(long, short)]
+    fail_if_empty: bool,
+    #[clap(long, value_enum, default_value_t)]
+    format: OutputFormat,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SnapshotPackagesCommand {
+    List(SnapshotPackagesListOpts),
+}
+
+impl SnapshotPackagesCommand {
+    pub async fn run(self, aptly: &AptlyRest) -> Result<ExitCode> {
+        match self {
+            SnapshotPackagesCommand::List(args) => match args.format {
+                OutputFormat::Name => {
+                    let mut keys = aptly
+                        .snapshot(&args.snapshot)
+                        .packages()
+                        .query(args.query, false)
+                        .list()
+                        .await?;
+                    if args.fail_if_empty && keys.is_empty() {
+                        return Ok(ExitCode::FAILURE);
+                    }
+
+                    keys.sort();
+                    for key in keys {
+                        println!("{}", key);
+                    }
+                }
+                OutputFormat::Json => {
+                    let results = aptly
+                        .snapshot(&args.snapshot)
+                        .packages()
+                        .query(args.query, false)
+                        .detailed()
+                        .await?;
+                    if args.fail_if_empty && results.is_empty() {
+                        return Ok(ExitCode::FAILURE);
+                    }
+
+                    serde_json::to_writer_pretty(&mut stdout(), &results)?;
+                }
+            },
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+#[derive(Parser, Debug)]
+pub struct SnapshotCreateOpts {
+    snapshot: String,
+    /// Package to include in the snapshot
+    #[clap(long = "key", short)]
+    keys: Vec<AptlyKey>,
+    /// Snapshot to record as a source of the new snapshot
+    #[clap(long = "source-snapshot")]
+    source_snapshots: Vec<String>,
+    #[clap(long)]
+    description: Option<String>,
+}
+
 #[derive(Parser, Debug)]
 pub struct SnapshotListOpts {
     #[clap(long, value_enum, default_value_t)]
@@ -19,6 +93,12 @@ pub struct SnapshotTestExistsOpts {
     snapshot: String,
 }
 
+#[derive(Parser, Debug)]
+pub struct SnapshotRenameOpts {
+    snapshot: String,
+    new_name: String,
+}
+
 #[derive(Parser, Debug)]
 pub struct SnapshotDropOpts {
     snapshot: String,
@@ -26,16 +106,200 @@ pub struct SnapshotDropOpts {
     force: bool,
 }
 
+#[derive(Parser, Debug)]
+pub struct SnapshotMergeOpts {
+    /// Snapshot to create
+    destination: String,
+    /// Snapshots to merge, later ones taking precedence
+    #[clap(required = true)]
+    sources: Vec<String>,
+    /// Only keep the latest version of each package
+    #[clap(long, conflicts_with = "no_remove")]
+    latest: bool,
+    /// Keep all versions of each package
+    #[clap(long)]
+    no_remove: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct SnapshotPullOpts {
+    /// Snapshot to pull packages into
+    snapshot: String,
+    /// Snapshot to pull packages from
+    source: String,
+    /// Snapshot to create
+    destination: String,
+    /// Package queries to pull
+    #[clap(required = true)]
+    queries: Vec<Query>,
+    #[clap(long = "architecture")]
+    architectures: Vec<String>,
+    /// Don't pull dependencies of the matching packages
+    #[clap(long)]
+    no_deps: bool,
+    /// Keep the existing versions of pulled packages
+    #[clap(long)]
+    no_remove: bool,
+    /// Pull all packages matching a query rather than only the latest one
+    #[clap(long)]
+    all_matches: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct SnapshotFilterOpts {
+    /// Snapshot to filter
+    snapshot: String,
+    /// Snapshot to create
+    destination: String,
+    /// Package queries to keep
+    #[clap(required = true)]
+    queries: Vec<Query>,
+    /// Also keep dependencies of the matching packages
+    #[clap(long)]
+    with_deps: bool,
+    #[clap(long)]
+    description: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct SnapshotDiffOpts {
+    /// Snapshot to compare from
+    snapshot: String,
+    /// Snapshot to compare to
+    other: String,
+    #[clap(long, value_enum, default_value_t)]
+    format: OutputFormat,
+}
+
+#[derive(Default)]
+struct GroupedDiff<'a> {
+    added: Vec<&'a AptlyKey>,
+    removed: Vec<&'a AptlyKey>,
+    upgraded: Vec<(&'a AptlyKey, &'a AptlyKey)>,
+    downgraded: Vec<(&'a AptlyKey, &'a AptlyKey)>,
+    // Replaced by the same version with different contents
+    rebuilt: Vec<(&'a AptlyKey, &'a AptlyKey)>,
+}
+
+impl<'a> GroupedDiff<'a> {
+    fn new(diff: &'a [SnapshotDiff]) -> Self {
+        let mut grouped = Self::default();
+        for entry in diff {
+            match (entry.left(), entry.right()) {
+                (None, Some(right)) => grouped.added.push(right),
+                (Some(left), None) => grouped.removed.push(left),
+                (Some(left), Some(right)) => match right.version().cmp(left.version()) {
+                    Ordering::Greater => grouped.upgraded.push((left, right)),
+                    Ordering::Less => grouped.downgraded.push((left, right)),
+                    Ordering::Equal => grouped.rebuilt.push((left, right)),
+                },
+                (None, None) => (),
+            }
+        }
+
+        grouped.added.sort();
+        grouped.removed.sort();
+        grouped.upgraded.sort();
+        grouped.downgraded.sort();
+        grouped.rebuilt.sort();
+        grouped
+    }
+
+    fn print_names(&self) {
+        for key in &self.added {
+            println!("added {key}");
+        }
+        for key in &self.removed {
+            println!("removed {key}");
+        }
+        for (kind, changes) in [
+            ("upgraded", &self.upgraded),
+            ("downgraded", &self.downgraded),
+        ] {
+            for (from, to) in changes {
+                println!(
+                    "{kind} {} {} {} -> {}",
+                    from.package(),
+                    from.arch(),
+                    from.version(),
+                    to.version()
+                );
+            }
+        }
+        for (from, to) in &self.rebuilt {
+            println!(
+                "rebuilt {} {} {} {} -> {}",
+                from.package(),
+                from.arch(),
+                from.version(),
+                from.hash(),
+                to.hash()
+            );
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        let changes = |changes: &[(&AptlyKey, &AptlyKey)]| -> Vec<serde_json::Value> {
+            changes
+                .iter()
+                .map(|(from, to)| {
+                    json!({
+                        "package": from.package(),
+                        "architecture": from.arch(),
+                        "from": from,
+                        "to": to,
+                    })
+                })
+                .collect()
+        };
+
+        json!({
+            "added": self.added,
+            "removed": self.removed,
+            "upgraded": changes(&self.upgraded),
+            "downgraded": changes(&self.downgraded),
+            "rebuilt": changes(&self.rebuilt),
+        })
+    }
+}
+
 #[derive(Subcommand, Debug)]
 pub enum SnapshotCommand {
+    Create(SnapshotCreateOpts),
     List(SnapshotListOpts),
+    #[clap(subcommand)]
+    Packages(SnapshotPackagesCommand),
     TestExists(SnapshotTestExistsOpts),
+    Merge(SnapshotMergeOpts),
+    Pull(SnapshotPullOpts),
+    Filter(SnapshotFilterOpts),
+    Diff(SnapshotDiffOpts),
+    Rename(SnapshotRenameOpts),
     Drop(SnapshotDropOpts),
 }
 
 impl SnapshotCommand {
     pub async fn run(self, aptly: &AptlyRest) -> Result<ExitCode> {
         match self {
+            SnapshotCommand::Create(args) => {
+                let snapshot = aptly
+                    .create_snapshot(
+                        &args.snapshot,
+                        &args.keys,
+                        &snapshots::CreateOptions {
+                            description: args.description,
+                            source_snapshots: args.source_snapshots,
+                        },
+                    )
+                    .await?;
+                info!(
+                    "Created snapshot '{}' with {} package(s)",
+                    snapshot.name(),
+                    args.keys.len()
+                );
+            }
+
+            SnapshotCommand::Packages(command) => return command.run(aptly).await,
             SnapshotCommand::List(args) => {
                 let snapshots = aptly.snapshots().await?;
                 match args.format {
@@ -53,18 +317,99 @@ impl SnapshotCommand {
                 }
             }
 
-            SnapshotCommand::TestExists(args) => {
-                if let Err(err) = aptly.snapshot(&args.snapshot).get().await {
-                    if let AptlyRestError::Request(err) = &err {
-                        if err.status() == Some(StatusCode::NOT_FOUND) {
-                            return Ok(ExitCode::FAILURE);
-                        }
-                    }
+            SnapshotCommand::TestExists(args) => match aptly.snapshot(&args.snapshot).get().await {
+                Ok(_) => (),
+                Err(AptlyRestError::NotFound { .. }) => return Ok(ExitCode::FAILURE),
+                Err(err) => return Err(err.into()),
+            },
+
+            SnapshotCommand::Merge(args) => {
+                let snapshot = aptly
+                    .merge_snapshots(
+                        &args.destination,
+                        &args.sources,
+                        &snapshots::MergeOptions {
+                            latest: args.latest,
+                            no_remove: args.no_remove,
+                        },
+                    )
+                    .await?;
+                info!(
+                    "Created snapshot '{}' by merging {}",
+                    snapshot.name(),
+                    args.sources.join(", ")
+                );
+            }
+
+            SnapshotCommand::Pull(args) => {
+                let snapshot = aptly
+                    .snapshot(&args.snapshot)
+                    .pull(
+                        &args.source,
+                        &args.destination,
+                        &args.queries,
+                        &snapshots::PullOptions {
+                            architectures: args.architectures,
+                            no_deps: args.no_deps,
+                            no_remove: args.no_remove,
+                            all_matches: args.all_matches,
+                        },
+                    )
+                    .await?;
+                info!(
+                    "Created snapshot '{}' from '{}' with packages pulled from '{}'",
+                    snapshot.name(),
+                    args.snapshot,
+                    args.source
+                );
+            }
 
-                    return Err(err.into());
+            SnapshotCommand::Filter(args) => {
+                let snapshot = aptly
+                    .snapshot(&args.snapshot)
+                    .filter(
+                        &args.destination,
+                        &args.queries,
+                        &snapshots::FilterOptions {
+                            with_deps: args.with_deps,
+                            description: args.description,
+                        },
+                    )
+                    .await?;
+                info!(
+                    "Created snapshot '{}' by filtering '{}'",
+                    snapshot.name(),
+                    args.snapshot
+                );
+            }
+
+            SnapshotCommand::Diff(args) => {
+                let diff = aptly.snapshot(&args.snapshot).diff(&args.other).await?;
+                let grouped = GroupedDiff::new(&diff);
+                match args.format {
+                    OutputFormat::Name => grouped.print_names(),
+                    OutputFormat::Json => {
+                        serde_json::to_writer_pretty(&mut stdout(), &grouped.to_json())?;
+                        println!();
+                    }
                 }
             }
 
+            SnapshotCommand::Rename(args) => {
+                let snapshot = aptly
+                    .snapshot(&args.snapshot)
+                    .edit(&snapshots::EditOptions {
+                        name: Some(args.new_name),
+                        ..Default::default()
+                    })
+                    .await?;
+                info!(
+                    "Renamed snapshot '{}' to '{}'",
+                    args.snapshot,
+                    snapshot.name()
+                );
+            }
+
             SnapshotCommand::Drop(args) => {
                 aptly
                     .snapshot(&args.snapshot)
@@ -0,0 +1,94 @@
+use std::{io::stdout, process::ExitCode};
+
+use aptly_rest::{AptlyRest, AptlyRestError};
+use clap::Parser;
+use color_eyre::Result;
+use serde_json::json;
+use tracing::warn;
+
+use crate::OutputFormat;
+
+#[derive(Parser, Debug)]
+pub struct StatusOpts {
+    #[clap(long, value_enum, default_value_t)]
+    format: OutputFormat,
+    /// Also show the metrics of the server
+    #[clap(long)]
+    metrics: bool,
+}
+
+impl StatusOpts {
+    pub async fn run(self, aptly: &AptlyRest) -> Result<ExitCode> {
+        let version = aptly.version().await?;
+        let healthy = aptly.healthy().await?;
+        let ready = aptly.ready().await?;
+        let storage = match aptly.storage().await {
+            Ok(storage) => Some(storage),
+            Err(AptlyRestError::NotFound { .. }) => None,
+            Err(err) => return Err(err.into()),
+        };
+        let metrics = if self.metrics {
+            match aptly.metrics().await {
+                Ok(metrics) => Some(metrics),
+                Err(AptlyRestError::NotFound { .. }) => {
+                    warn!("The metrics endpoint of the server isn't enabled");
+                    None
+                }
+                Err(err) => return Err(err.into()),
+            }
+        } else {
+            None
+        };
+
+        match self.format {
+            OutputFormat::Name => {
+                let yes_no = |b| if b { "yes" } else { "no" };
+                println!("Version: {version}");
+                println!("Healthy: {}", yes_no(healthy));
+                println!("Ready: {}", yes_no(ready));
+                if let Some(storage) = &storage {
+                    println!(
+                        "Storage: {} MiB free of {} MiB ({:.1}% full)",
+                        storage.free(),
+                        storage.total(),
+                        storage.percent_full()
+                    );
+                }
+                for sample in metrics.iter().flat_map(|m| m.samples()) {
+                    let labels: Vec<_> = sample
+                        .labels()
+                        .iter()
+                        .map(|(k, v)| format!("{k}={v:?}"))
+                        .collect();
+                    if labels.is_empty() {
+                        println!("{} {}", sample.name(), sample.value());
+                    } else {
+                        println!(
+                            "{}{{{}}} {}",
+                            sample.name(),
+                            labels.join(","),
+                            sample.value()
+                        );
+                    }
+                }
+            }
+            OutputFormat::Json => {
+                let status = json!({
+                    "Version": version.to_string(),
+                    "Healthy": healthy,
+                    "Ready": ready,
+                    "Storage": storage,
+                    "Metrics": metrics,
+                });
+                serde_json::to_writer_pretty(&mut stdout(), &status)?;
+                println!();
+            }
+        }
+
+        if healthy && ready {
+            Ok(ExitCode::SUCCESS)
+        } else {
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
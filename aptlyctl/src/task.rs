@@ -0,0 +1,102 @@
+use std::{io::stdout, process::ExitCode, time::Duration};
+
+use aptly_rest::{api::tasks::TaskState, AptlyRest};
+use clap::{Parser, Subcommand};
+use color_eyre::Result;
+use tracing::{error, info};
+
+use crate::OutputFormat;
+
+#[derive(Parser, Debug)]
+pub struct TaskListOpts {
+    #[clap(long, value_enum, default_value_t)]
+    format: OutputFormat,
+}
+
+#[derive(Parser, Debug)]
+pub struct TaskShowOpts {
+    id: u64,
+    #[clap(long, value_enum, default_value_t)]
+    format: OutputFormat,
+}
+
+#[derive(Parser, Debug)]
+pub struct TaskWaitOpts {
+    id: u64,
+    /// Give up after this many seconds
+    #[clap(long)]
+    timeout: Option<u64>,
+}
+
+#[derive(Parser, Debug)]
+pub struct TaskOutputOpts {
+    id: u64,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TaskCommand {
+    List(TaskListOpts),
+    Show(TaskShowOpts),
+    Wait(TaskWaitOpts),
+    Output(TaskOutputOpts),
+    Clear,
+}
+
+impl TaskCommand {
+    pub async fn run(self, aptly: &AptlyRest) -> Result<ExitCode> {
+        match self {
+            TaskCommand::List(args) => {
+                let tasks = aptly.tasks().await?;
+                match args.format {
+                    OutputFormat::Name => {
+                        for task in tasks {
+                            println!("{} {:?} {}", task.id(), task.state(), task.name());
+                        }
+                    }
+                    OutputFormat::Json => {
+                        serde_json::to_writer_pretty(&mut stdout(), &tasks)?;
+                        println!();
+                    }
+                }
+            }
+
+            TaskCommand::Show(args) => {
+                let task = aptly.task(args.id).get().await?;
+                match args.format {
+                    OutputFormat::Name => {
+                        println!("{} {:?} {}", task.id(), task.state(), task.name());
+                    }
+                    OutputFormat::Json => {
+                        serde_json::to_writer_pretty(&mut stdout(), &task)?;
+                        println!();
+                    }
+                }
+            }
+
+            TaskCommand::Wait(args) => {
+                let task_api = aptly.task(args.id);
+                let task = match args.timeout {
+                    Some(timeout) => task_api.wait_timeout(Duration::from_secs(timeout)).await?,
+                    None => task_api.wait().await?,
+                };
+                if task.state() == TaskState::Failed {
+                    error!("Task {} '{}' failed:", task.id(), task.name());
+                    eprint!("{}", task_api.output().await?);
+                    return Ok(ExitCode::FAILURE);
+                }
+                info!("Task {} '{}' {:?}", task.id(), task.name(), task.state());
+            }
+
+            TaskCommand::Output(args) => {
+                print!("{}", aptly.task(args.id).output().await?);
+            }
+
+            TaskCommand::Clear => {
+                aptly.tasks_clear().await?;
+                info!("Cleared finished tasks");
+            }
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
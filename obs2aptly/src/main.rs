@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use aptly_rest::AptlyRest;
+use aptly_rest::args::AptlyArgs;
 use clap::Parser;
 use color_eyre::Result;
 use sync2aptly::{AptlyContent, PoolPackagesCache, UploadOptions};
@@ -16,17 +16,8 @@ enum FilterKind {
 
 #[derive(Parser, Debug)]
 struct Opts {
-    /// Url for the aptly rest api endpoint
-    #[clap(
-        short = 'u',
-        long,
-        env = "APTLY_API_URL",
-        default_value = "http://localhost:8080"
-    )]
-    api_url: url::Url,
-    /// Authentication token for the API
-    #[clap(long, env = "APTLY_API_TOKEN")]
-    api_token: Option<String>,
+    #[clap(flatten)]
+    aptly: AptlyArgs,
     /// Repo in aptly
     aptly_repo: String,
     /// Directory with obs repositories
@@ -34,6 +25,9 @@ struct Opts {
     /// Maximum number of parallel uploads
     #[clap(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..))]
     max_parallel_uploads: u8,
+    /// Run long aptly operations as background tasks
+    #[clap(long)]
+    async_tasks: bool,
     /// Only sync files of the given type
     #[clap(long)]
     only: Option<FilterKind>,
@@ -50,11 +44,7 @@ async fn main() -> Result<()> {
         .init();
     color_eyre::install().unwrap();
     let opts = Opts::parse();
-    let aptly = if let Some(token) = opts.api_token {
-        AptlyRest::new_with_token(opts.api_url, &token)?
-    } else {
-        AptlyRest::new(opts.api_url)
-    };
+    let aptly = opts.aptly.build()?;
 
     let aptly_contents = AptlyContent::new_from_aptly(&aptly, opts.aptly_repo).await?;
     let pool_packages = PoolPackagesCache::new(aptly.clone());
@@ -81,6 +71,7 @@ async fn main() -> Result<()> {
                 "obs2aptly",
                 &UploadOptions {
                     max_parallel: opts.max_parallel_uploads,
+                    async_tasks: opts.async_tasks,
                 },
             )
             .await?;
@@ -4,7 +4,7 @@ use color_eyre::{
     Report, Result,
 };
 use debian_packaging::package_version::PackageVersion;
-use futures::{stream::FuturesUnordered, Future, FutureExt, StreamExt};
+use futures::{stream::FuturesUnordered, Future, FutureExt, StreamExt, TryStreamExt};
 use http::StatusCode;
 use once_cell::sync::OnceCell;
 use reqwest::Client;
@@ -24,9 +24,10 @@ use tracing::{debug, error, info, warn};
 use url::Url;
 
 use aptly_rest::{
-    api::{files::UploadFiles, packages},
+    api::{files::UploadFiles, packages, repos::AddPackageResponse},
     dsc::DscFile,
     key::AptlyKey,
+    query::Query,
     AptlyRest, AptlyRestError,
 };
 
@@ -115,11 +116,11 @@ impl AptlyContent {
 
     #[tracing::instrument]
     pub async fn new_from_aptly(aptly: &AptlyRest, repo: String) -> Result<Self> {
-        let packages = aptly.repo(&repo).packages().list().await?;
+        let mut packages = aptly.repo(&repo).packages().list_stream();
         let mut content = Self::new_empty(repo);
 
-        for p in packages {
-            content.add_key(p);
+        while let Some(key) = packages.try_next().await? {
+            content.add_key(key);
         }
         Ok(content)
     }
@@ -688,12 +689,10 @@ impl PoolPackagesCache {
         const CHUNK_SIZE: usize = 1000;
 
         for chunk in query_parts.chunks(CHUNK_SIZE) {
-            let query = chunk.to_vec().join("|");
-            let aptly_packages = self.aptly.packages().query(query, false).detailed().await?;
+            let query = Query::Or(chunk.iter().map(|name| Query::package(*name)).collect());
+            let mut aptly_packages = self.aptly.packages().query(query, false).detailed_stream();
 
-            let mut packages_by_name = self.packages_by_name.write().unwrap();
-
-            for package in aptly_packages {
+            while let Some(package) = aptly_packages.try_next().await? {
                 let filename = match &package {
                     packages::Package::Source(source) => source
                         .sha256_files()
@@ -704,7 +703,9 @@ impl PoolPackagesCache {
                     packages::Package::Binary(binary) => binary.filename(),
                 };
 
-                packages_by_name
+                self.packages_by_name
+                    .write()
+                    .unwrap()
                     .entry(package.package().to_owned())
                     .or_default()
                     .insert(PoolPackage {
@@ -831,9 +832,17 @@ pub struct AddDebOptions {
 #[derive(Default)]
 pub struct UploadOptions {
     pub max_parallel: u8,
+    /// Add the uploaded packages to the repository using an aptly background
+    /// task rather than a single long-running request
+    pub async_tasks: bool,
 }
 
 fn is_reqwest_error_retriable(e: &reqwest::Error) -> bool {
+    // Retrying a request that can't be built or a response that can't be
+    // decoded won't make it work
+    if e.is_builder() || e.is_decode() || e.is_redirect() {
+        return false;
+    }
     !e.status().as_ref().is_some_and(StatusCode::is_client_error)
 }
 
@@ -1048,7 +1057,11 @@ impl SyncActions {
                 .await
                 .map_err::<BackoffError<Report>, _>(|e| match &e {
                     AptlyRestError::Request(r) if is_reqwest_error_retriable(r) => {
-                        warn!("Failed to upload {filename}: {}", r);
+                        warn!("Failed to upload {filename}: {}", e);
+                        BackoffError::transient(e.into())
+                    }
+                    AptlyRestError::Server { .. } => {
+                        warn!("Failed to upload {filename}: {}", e);
                         BackoffError::transient(e.into())
                     }
                     _ => BackoffError::permanent(e.into()),
@@ -1067,18 +1080,15 @@ impl SyncActions {
             return Ok(());
         }
 
-        if let Err(err) = self
+        match self
             .aptly
             .files()
             .directory(upload_dir.to_owned())
             .delete()
             .await
         {
-            if let AptlyRestError::Request(inner) = &err {
-                if inner.status() != Some(http::StatusCode::NOT_FOUND) {
-                    return Err(err.into());
-                }
-            }
+            Ok(()) | Err(AptlyRestError::NotFound { .. }) => (),
+            Err(err) => return Err(err.into()),
         }
 
         let mut uploaded_packages = 0;
@@ -1149,12 +1159,21 @@ impl SyncActions {
                 uploaded_packages
             );
 
-            let response = self
-                .aptly
-                .repo(&self.repo)
-                .files()
-                .add_directory(upload_dir, &Default::default())
-                .await?;
+            let response: AddPackageResponse = if upload_options.async_tasks {
+                self.aptly
+                    .repo(&self.repo)
+                    .files()
+                    .add_directory_async(upload_dir, &Default::default())
+                    .await?
+                    .result()
+                    .await?
+            } else {
+                self.aptly
+                    .repo(&self.repo)
+                    .files()
+                    .add_directory(upload_dir, &Default::default())
+                    .await?
+            };
             debug!(?response);
 
             let warnings = response.report().warnings();